@@ -0,0 +1,102 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::f64::consts::PI;
+
+/// Uniform external field (e.g. electric or magnetic) that rotates
+/// moving particles towards a preferred direction and may favour
+/// growth along it
+#[derive(Copy, Clone, PartialEq)]
+pub struct AligningField {
+    // preferred particle rotation, degrees
+    direction: f64,
+    // maximum angular velocity caused by the field, degrees per time unit
+    torque: f64,
+    // particle rotational symmetry: n-fold particles have n equivalent
+    // aligned rotations, 360 / n degrees apart
+    order: u8,
+    // fraction of misalignment removed when a particle attaches, [0, 1]
+    binding_bias: f64,
+    // how strongly growth across the field direction is suppressed, [0, 1]
+    growth_bias: f64,
+}
+
+impl AligningField {
+    pub fn new(
+        direction: f64,
+        torque: f64,
+        order: u8,
+        binding_bias: f64,
+        growth_bias: f64,
+    ) -> Self {
+        Self {
+            direction,
+            torque,
+            order: order.max(1),
+            binding_bias: binding_bias.clamp(0., 1.),
+            growth_bias: growth_bias.clamp(0., 1.),
+        }
+    }
+
+    /// angular velocity induced in a particle with given rotation (degrees)
+    pub fn spin(&self, rot: f64) -> f64 {
+        -self.torque * (self.misalignment(rot) * self.order as f64 * PI / 180.).sin()
+    }
+
+    /// signed angle from a particle rotation to the closest aligned rotation,
+    /// within half a symmetry period
+    pub fn misalignment(&self, rot: f64) -> f64 {
+        let period = 360. / self.order as f64;
+        (rot - self.direction + period / 2.).rem_euclid(period) - period / 2.
+    }
+
+    /// rotation of an attaching particle, turned towards the closest aligned rotation
+    pub fn bias_rotation(&self, rot: f64) -> f64 {
+        rot - self.binding_bias * self.misalignment(rot)
+    }
+
+    /// probability to accept a new bond in given direction (degrees):
+    /// 1 along the field, 1 - growth_bias across it
+    pub fn growth_probability(&self, bond_direction: f64) -> f64 {
+        let cos = ((bond_direction - self.direction) * PI / 180.).cos();
+        1. - self.growth_bias * (1. - cos * cos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn alignment_torque() {
+        let field = AligningField::new(30., 10., 6, 0.5, 0.75);
+        assert!(field.spin(30.).abs() < 1e-9);
+        assert!(
+            field.spin(90.).abs() < 1e-9,
+            "Hexagon rotated by 60 is aligned"
+        );
+        assert!((field.spin(45.) + 10.).abs() < 1e-9, "Not turning back");
+        assert!((field.spin(15.) - 10.).abs() < 1e-9, "Not turning forward");
+
+        assert!((field.misalignment(85.) + 5.).abs() < 1e-9);
+        assert!((field.misalignment(-20.) - 10.).abs() < 1e-9);
+
+        assert!((field.bias_rotation(50.) - 40.).abs() < 1e-9);
+        assert!((field.growth_probability(210.) - 1.).abs() < 1e-9);
+        assert!((field.growth_probability(120.) - 0.25).abs() < 1e-9);
+    }
+}
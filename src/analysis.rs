@@ -0,0 +1,260 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+use crate::vector::Vector;
+
+/// Least number of scales to fit a power law to
+const MIN_FIT_POINTS: usize = 3;
+
+/// Mass-radius fit only covers this fraction of the crystal radius:
+/// outer branches are still growing and thin out
+const MASS_RADIUS_EXTENT: f64 = 0.5;
+
+/// Smallest particle count to measure radius of gyration at
+const MIN_GYRATION_COUNT: usize = 8;
+
+/// A measured value and quality of the fit it comes from
+/// (coefficient of determination, 0 when there is not enough data)
+#[wasm_bindgen]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Measure {
+    pub value: f64,
+    pub fit_quality: f64,
+}
+
+/// Shape metrics of a crystal
+#[wasm_bindgen]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct FractalAnalysis {
+    /// number of particles analysed
+    pub particles: u32,
+    /// dimension from the number of occupied boxes of different sizes
+    pub box_counting: Measure,
+    /// dimension from the number of particles within distance from the center of mass
+    pub mass_radius: Measure,
+    /// dimension from radius of gyration growth with particle count (in order of attachment)
+    pub gyration: Measure,
+    /// root mean square distance from the center of mass
+    pub radius_of_gyration: f64,
+}
+
+impl FractalAnalysis {
+    /// analyse particles in order of attachment; scale is the distance between bound particles
+    pub fn new(points: &[Vector], scale: f64) -> Self {
+        Self {
+            particles: points.len() as u32,
+            box_counting: box_counting_dimension(points, scale),
+            mass_radius: mass_radius_dimension(points, scale),
+            gyration: gyration_dimension(points),
+            radius_of_gyration: radius_of_gyration(points),
+        }
+    }
+}
+
+/// fit y = c * x^exponent in log-log scale, returns the exponent
+fn fit_power_law(samples: &[(f64, f64)]) -> Measure {
+    if samples.len() < MIN_FIT_POINTS {
+        return Measure::default();
+    }
+    let logs = samples
+        .iter()
+        .map(|&(x, y)| (x.ln(), y.ln()))
+        .collect::<Vec<_>>();
+    let n = logs.len() as f64;
+    let mean_x = logs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = logs.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx = logs.iter().map(|p| (p.0 - mean_x).powi(2)).sum::<f64>();
+    let sxy = logs
+        .iter()
+        .map(|p| (p.0 - mean_x) * (p.1 - mean_y))
+        .sum::<f64>();
+    let syy = logs.iter().map(|p| (p.1 - mean_y).powi(2)).sum::<f64>();
+    if sxx == 0. {
+        return Measure::default();
+    }
+    let slope = sxy / sxx;
+    Measure {
+        value: slope,
+        // all samples equal fit any line perfectly
+        fit_quality: if syy > 0. {
+            sxy * sxy / (sxx * syy)
+        } else {
+            1.
+        },
+    }
+}
+
+fn center_of_mass(points: &[Vector]) -> Vector {
+    let total = points
+        .iter()
+        .fold(Vector::new(0., 0.), |sum, &point| sum + point);
+    total * (1. / points.len().max(1) as f64)
+}
+
+/// box-counting dimension, with box sizes doubling from scale up to a quarter of crystal extent
+pub fn box_counting_dimension(points: &[Vector], scale: f64) -> Measure {
+    let (min, max) = points.iter().fold(
+        (
+            Vector::new(f64::INFINITY, f64::INFINITY),
+            Vector::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        ),
+        |(min, max), p| {
+            (
+                Vector::new(min.x.min(p.x), min.y.min(p.y)),
+                Vector::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+    let extent = (max.x - min.x).max(max.y - min.y);
+    let mut samples = Vec::new();
+    let mut size = scale;
+    while size > 0. && size <= extent / 4. {
+        let boxes = points
+            .iter()
+            .map(|p| {
+                (
+                    ((p.x - min.x) / size).floor() as i64,
+                    ((p.y - min.y) / size).floor() as i64,
+                )
+            })
+            .collect::<HashSet<_>>();
+        samples.push((1. / size, boxes.len() as f64));
+        size *= 2.;
+    }
+    fit_power_law(&samples)
+}
+
+/// mass-radius dimension around the center of mass, with radii growing from 2 * scale
+pub fn mass_radius_dimension(points: &[Vector], scale: f64) -> Measure {
+    let center = center_of_mass(points);
+    let mut distances = points
+        .iter()
+        .map(|p| Vector::length(&Vector::diff(p, &center)))
+        .collect::<Vec<_>>();
+    distances.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let outer = distances.last().copied().unwrap_or(0.) * MASS_RADIUS_EXTENT;
+
+    let mut samples = Vec::new();
+    let mut radius = 2. * scale;
+    while radius > 0. && radius <= outer {
+        let mass = distances.partition_point(|&distance| distance <= radius);
+        if mass > 0 {
+            samples.push((radius, mass as f64));
+        }
+        radius *= std::f64::consts::SQRT_2;
+    }
+    fit_power_law(&samples)
+}
+
+/// root mean square distance from the center of mass
+pub fn radius_of_gyration(points: &[Vector]) -> f64 {
+    let center = center_of_mass(points);
+    let total = points
+        .iter()
+        .map(|p| {
+            let d = Vector::diff(p, &center);
+            Vector::dot(&d, &d)
+        })
+        .sum::<f64>();
+    (total / points.len().max(1) as f64).sqrt()
+}
+
+/// dimension D from radius of gyration of the first n particles, Rg ~ n^(1/D),
+/// with n doubling
+pub fn gyration_dimension(points: &[Vector]) -> Measure {
+    let mut samples = Vec::new();
+    // running sums give radius of gyration for every prefix
+    let (mut sum, mut sum_squares) = (Vector::new(0., 0.), 0.);
+    let mut next_count = MIN_GYRATION_COUNT;
+    for (index, &p) in points.iter().enumerate() {
+        sum += p;
+        sum_squares += Vector::dot(&p, &p);
+        let count = index + 1;
+        if count == next_count {
+            let mean = sum * (1. / count as f64);
+            let rg = (sum_squares / count as f64 - Vector::dot(&mean, &mean))
+                .max(0.)
+                .sqrt();
+            if rg > 0. {
+                samples.push((count as f64, rg));
+            }
+            next_count *= 2;
+        }
+    }
+    let fit = fit_power_law(&samples);
+    if fit.value > 0. {
+        Measure {
+            value: 1. / fit.value,
+            ..fit
+        }
+    } else {
+        Measure::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn fractal_dimensions() {
+        let line = (0..256)
+            .map(|i| Vector::new(i as f64, 0.))
+            .collect::<Vec<_>>();
+        let analysis = FractalAnalysis::new(&line, 1.);
+        assert_eq!(analysis.particles, 256);
+        for measure in &[
+            analysis.box_counting,
+            analysis.mass_radius,
+            analysis.gyration,
+        ] {
+            assert!((measure.value - 1.).abs() < 0.05, "{:?}", measure);
+            assert!(measure.fit_quality > 0.99, "{:?}", measure);
+        }
+        assert!((analysis.radius_of_gyration - 255. / 12f64.sqrt()).abs() < 0.5);
+
+        // filled disk, growing outwards
+        let mut disk = Vec::new();
+        for x in -50..=50 {
+            for y in -50..=50 {
+                if x * x + y * y <= 2500 {
+                    disk.push(Vector::new(x as f64, y as f64));
+                }
+            }
+        }
+        disk.sort_by(|a, b| Vector::length(a).partial_cmp(&Vector::length(b)).unwrap());
+        let analysis = FractalAnalysis::new(&disk, 1.);
+        for measure in &[
+            analysis.box_counting,
+            analysis.mass_radius,
+            analysis.gyration,
+        ] {
+            assert!((measure.value - 2.).abs() < 0.15, "{:?}", measure);
+            assert!(measure.fit_quality > 0.98, "{:?}", measure);
+        }
+
+        assert_eq!(
+            FractalAnalysis::new(&line[..2], 1.)
+                .box_counting
+                .fit_quality,
+            0.
+        );
+    }
+}
@@ -0,0 +1,334 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+use crate::extfn;
+use crate::geometry;
+use crate::vector::Vector;
+
+// particles spawned exactly on the boundary may end up a rounding error outside
+const CONTAINMENT_TOLERANCE: f64 = 1e-9;
+// random positions tried before giving up on a field that is (almost) all outside
+// of its bounding rectangle
+const SAMPLE_ATTEMPTS: usize = 1000;
+
+/// What happens to a moving particle that crosses the field boundary
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum BoundaryBehaviour {
+    /// bounce back into the field
+    Reflect,
+    /// remove the particle
+    Absorb,
+    /// reappear at the opposite side of a rectangular field;
+    /// other shapes reflect instead, as wrapping could leave them
+    Periodic,
+}
+
+/// Where particles added with `add_boundary_particle` appear
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum SpawnRegion {
+    /// anywhere on the outer field boundary
+    Boundary,
+    /// in a thin band along the top of the field (for deposition under gravity)
+    Top,
+}
+
+/// Field boundary shape, centered at (0, 0)
+#[derive(Clone, PartialEq)]
+pub enum Boundary {
+    /// ellipse with given half-axes
+    Ellipse(Vector),
+    /// rectangle with given half-width and half-height
+    Rectangle(Vector),
+    /// simple polygon, vertices in order
+    Polygon(Vec<Vector>),
+    /// ring between two circles
+    Annulus { inner: f64, outer: f64 },
+}
+
+impl Boundary {
+    /// half-dimensions of the bounding rectangle
+    pub fn half_extents(&self) -> Vector {
+        match self {
+            Boundary::Ellipse(half) | Boundary::Rectangle(half) => *half,
+            Boundary::Polygon(vertices) => vertices.iter().fold(Vector::new(0., 0.), |acc, v| {
+                Vector::new(acc.x.max(v.x.abs()), acc.y.max(v.y.abs()))
+            }),
+            Boundary::Annulus { outer, .. } => Vector::new(*outer, *outer),
+        }
+    }
+
+    /// check that the shape has a positive finite area
+    pub fn is_valid(&self) -> bool {
+        let positive = |x: f64| x > 0. && x.is_finite();
+        match self {
+            Boundary::Ellipse(half) | Boundary::Rectangle(half) => {
+                positive(half.x) && positive(half.y)
+            }
+            Boundary::Polygon(vertices) => {
+                vertices.len() >= 3 && positive(geometry::polygon_signed_area(vertices).abs())
+            }
+            Boundary::Annulus { inner, outer } => *inner >= 0. && positive(outer - inner),
+        }
+    }
+
+    /// outline polygon, counter-clockwise; curves are approximated with a number
+    /// of vertices, and the hole of an annulus is left out
+    pub fn outline(&self, segments: usize) -> Vec<Vector> {
+        let ellipse = |half: Vector| {
+            (0..segments.max(3))
+                .map(|i| {
+                    let (sin, cos) = (2. * PI * i as f64 / segments.max(3) as f64).sin_cos();
+                    Vector::new(half.x * cos, half.y * sin)
+                })
+                .collect()
+        };
+        match self {
+            Boundary::Ellipse(half) => ellipse(*half),
+            Boundary::Rectangle(half) => vec![
+                Vector::new(-half.x, -half.y),
+                Vector::new(half.x, -half.y),
+                Vector::new(half.x, half.y),
+                Vector::new(-half.x, half.y),
+            ],
+            Boundary::Polygon(vertices) => {
+                let mut outline = vertices.clone();
+                if geometry::polygon_signed_area(&outline) < 0. {
+                    outline.reverse();
+                }
+                outline
+            }
+            Boundary::Annulus { outer, .. } => ellipse(Vector::new(*outer, *outer)),
+        }
+    }
+
+    /// check if a position is within the field
+    pub fn contains(&self, pos: &Vector) -> bool {
+        match self {
+            Boundary::Ellipse(half) => {
+                pos.x * pos.x / (half.x * half.x) + pos.y * pos.y / (half.y * half.y)
+                    <= 1. + CONTAINMENT_TOLERANCE
+            }
+            Boundary::Rectangle(half) => {
+                pos.x.abs() <= half.x * (1. + CONTAINMENT_TOLERANCE)
+                    && pos.y.abs() <= half.y * (1. + CONTAINMENT_TOLERANCE)
+            }
+            Boundary::Polygon(vertices) => {
+                // points exactly on an edge count as inside
+                let closest = geometry::closest_polygon_edge(vertices, pos).1;
+                Vector::distance_squared(&closest, pos).sqrt() <= CONTAINMENT_TOLERANCE
+                    || geometry::polygon_contains(vertices, pos)
+            }
+            Boundary::Annulus { inner, outer } => {
+                let r = Vector::length(pos);
+                r >= inner * (1. - CONTAINMENT_TOLERANCE)
+                    && r <= outer * (1. + CONTAINMENT_TOLERANCE)
+            }
+        }
+    }
+
+    /// outward normal (unit length) of the boundary part closest to a position
+    pub fn normal(&self, pos: &Vector) -> Vector {
+        let towards = |v: Vector| {
+            if Vector::length(&v) > 0. {
+                Vector::normalize(v)
+            } else {
+                Vector::new(1., 0.)
+            }
+        };
+        match self {
+            Boundary::Ellipse(half) => towards(Vector::new(
+                pos.x / (half.x * half.x),
+                pos.y / (half.y * half.y),
+            )),
+            Boundary::Rectangle(half) => {
+                if pos.x.abs() / half.x >= pos.y.abs() / half.y {
+                    Vector::new(pos.x.signum(), 0.)
+                } else {
+                    Vector::new(0., pos.y.signum())
+                }
+            }
+            Boundary::Polygon(vertices) => {
+                let (edge, _) = geometry::closest_polygon_edge(vertices, pos);
+                let (a, b) = geometry::polygon_edges(vertices).nth(edge).unwrap();
+                // edge perpendicular, pointing outwards for counter-clockwise polygons
+                let normal = towards(Vector::new(b.y - a.y, a.x - b.x));
+                if geometry::polygon_signed_area(vertices) >= 0. {
+                    normal
+                } else {
+                    normal * -1.
+                }
+            }
+            Boundary::Annulus { inner, outer } => {
+                let radial = towards(*pos);
+                if Vector::length(pos) < (inner + outer) / 2. {
+                    // inner circle boundary faces the center
+                    radial * -1.
+                } else {
+                    radial
+                }
+            }
+        }
+    }
+
+    /// only a rectangle tiles the plane, so that wrapped positions stay inside
+    pub fn is_periodic(&self) -> bool {
+        matches!(self, Boundary::Rectangle(_))
+    }
+
+    /// wrap a position around the bounding rectangle (periodic boundary)
+    pub fn wrap(&self, pos: &Vector) -> Vector {
+        let half = self.half_extents();
+        Vector::new(
+            (pos.x + half.x).rem_euclid(2. * half.x) - half.x,
+            (pos.y + half.y).rem_euclid(2. * half.y) - half.y,
+        )
+    }
+
+    /// generate random position in the field, None if none was found
+    pub fn random_pos(&self) -> Option<Vector> {
+        let half = self.half_extents();
+        (0..SAMPLE_ATTEMPTS)
+            .map(|_| Vector {
+                x: (extfn::random() * 2. - 1.) * half.x,
+                y: (extfn::random() * 2. - 1.) * half.y,
+            })
+            .find(|v| self.contains(v))
+    }
+
+    /// generate random position on the outer field boundary
+    pub fn random_boundary_pos(&self) -> Vector {
+        match self {
+            Boundary::Ellipse(half) => {
+                // this is slightly wrong, because densities will skew in a truly elliptical field
+                let (sin, cos) = (extfn::random() * 2. * PI).sin_cos();
+                Vector::new(sin * half.x, cos * half.y)
+            }
+            Boundary::Rectangle(half) => Self::random_perimeter_pos(&[
+                Vector::new(-half.x, -half.y),
+                Vector::new(half.x, -half.y),
+                Vector::new(half.x, half.y),
+                Vector::new(-half.x, half.y),
+            ]),
+            Boundary::Polygon(vertices) => Self::random_perimeter_pos(vertices),
+            Boundary::Annulus { outer, .. } => {
+                let (sin, cos) = (extfn::random() * 2. * PI).sin_cos();
+                Vector::new(sin * outer, cos * outer)
+            }
+        }
+    }
+
+    /// generate random position in a horizontal band of given height
+    /// at the top (negative y) of the field, None if none was found
+    pub fn random_top_pos(&self, band: f64) -> Option<Vector> {
+        let half = self.half_extents();
+        let band = band.min(2. * half.y);
+        (0..SAMPLE_ATTEMPTS)
+            .map(|_| Vector {
+                x: (extfn::random() * 2. - 1.) * half.x,
+                y: -half.y + extfn::random() * band,
+            })
+            .find(|v| self.contains(v))
+    }
+
+    /// uniformly distributed position on a closed polyline
+    fn random_perimeter_pos(vertices: &[Vector]) -> Vector {
+        let perimeter: f64 = geometry::polygon_edges(vertices)
+            .map(|(a, b)| Vector::length(&Vector::diff(&b, &a)))
+            .sum();
+        let mut along = extfn::random() * perimeter;
+        for (a, b) in geometry::polygon_edges(vertices) {
+            let length = Vector::length(&Vector::diff(&b, &a));
+            if along <= length && length > 0. {
+                return a + Vector::diff(&b, &a) * (along / length);
+            }
+            along -= length;
+        }
+        vertices[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn containment() {
+        let ellipse = Boundary::Ellipse(Vector::new(20., 10.));
+        assert!(ellipse.contains(&Vector::new(19., 0.)));
+        assert!(!ellipse.contains(&Vector::new(0., 11.)));
+
+        let ring = Boundary::Annulus {
+            inner: 5.,
+            outer: 10.,
+        };
+        assert!(!ring.contains(&Vector::new(1., 1.)));
+        assert!(ring.contains(&Vector::new(7., 0.)));
+        assert!(ring.normal(&Vector::new(6., 0.)) == Vector::new(-1., 0.));
+
+        // clockwise square with a notch
+        let notched = Boundary::Polygon(vec![
+            Vector::new(-10., -10.),
+            Vector::new(-10., 10.),
+            Vector::new(10., 10.),
+            Vector::new(10., -10.),
+            Vector::new(0., 0.),
+        ]);
+        assert!(notched.contains(&Vector::new(-5., 0.)));
+        assert!(!notched.contains(&Vector::new(0., -5.)));
+        assert!(notched.normal(&Vector::new(0., 11.)) == Vector::new(0., 1.));
+        assert!(notched.half_extents() == Vector::new(10., 10.));
+
+        let rect = Boundary::Rectangle(Vector::new(10., 5.));
+        assert!(rect.normal(&Vector::new(11., 1.)) == Vector::new(1., 0.));
+        assert!(rect.wrap(&Vector::new(11., -6.)) == Vector::new(-9., 4.));
+        for _ in 0..100 {
+            assert!(rect.contains(&rect.random_boundary_pos()));
+            assert!(notched.contains(&notched.random_pos().unwrap()));
+            let top = rect.random_top_pos(1.).unwrap();
+            assert!(rect.contains(&top) && top.y <= -4.);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn degenerate_shapes() {
+        let inverted = Boundary::Annulus {
+            inner: 10.,
+            outer: 5.,
+        };
+        assert!(!inverted.is_valid());
+        assert!(inverted.random_pos().is_none());
+        assert!(inverted.random_top_pos(1.).is_none());
+        assert!(Boundary::Annulus {
+            inner: 0.,
+            outer: 5.
+        }
+        .is_valid());
+        assert!(!Boundary::Rectangle(Vector::new(10., 0.)).is_valid());
+        assert!(!Boundary::Ellipse(Vector::new(f64::NAN, 1.)).is_valid());
+        let flat = Boundary::Polygon(vec![
+            Vector::new(0., 0.),
+            Vector::new(5., 0.),
+            Vector::new(10., 0.),
+        ]);
+        assert!(!flat.is_valid());
+    }
+}
@@ -0,0 +1,272 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::fmt::{Display, Write};
+use wasm_bindgen::prelude::*;
+
+/// Busy ports histogram bins: 0 to 8 busy ports
+const PORT_BINS: usize = 9;
+
+/// Which static particle every static particle is bound to,
+/// indexed the same way as the static particle container
+#[derive(Clone, Default)]
+pub struct BondTree {
+    // parent index, None for seeds
+    parents: Vec<Option<usize>>,
+    // number of bonds to the seed
+    generations: Vec<u32>,
+}
+
+impl BondTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// remember the parent of a static particle
+    pub fn record(&mut self, index: usize, parent: Option<usize>) {
+        if self.parents.len() <= index {
+            self.parents.resize(index + 1, None);
+            self.generations.resize(index + 1, 0);
+        }
+        self.parents[index] = parent;
+        self.generations[index] = parent.map_or(0, |parent| self.generation(parent) + 1);
+    }
+
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.parents.get(index).copied().flatten()
+    }
+
+    /// number of bonds between a particle and its seed
+    pub fn generation(&self, index: usize) -> u32 {
+        self.generations.get(index).copied().unwrap_or(0)
+    }
+
+    /// children of every particle, for particles 0..count
+    fn children(&self, count: usize) -> Vec<Vec<usize>> {
+        let mut children = vec![Vec::new(); count];
+        for index in 0..count {
+            if let Some(parent) = self.parent(index).filter(|&parent| parent < count) {
+                children[parent].push(index);
+            }
+        }
+        children
+    }
+
+    /// Horton-Strahler order of every particle: tips have order 1, a particle
+    /// with two or more children of the highest order gets the next order
+    pub fn strahler_orders(&self, count: usize) -> Vec<u32> {
+        let children = self.children(count);
+        let mut orders = vec![1; count];
+        // parents are always added before their children
+        for index in (0..count).rev() {
+            let highest = children[index].iter().map(|&child| orders[child]).max();
+            if let Some(highest) = highest {
+                let ties = children[index]
+                    .iter()
+                    .filter(|&&child| orders[child] == highest)
+                    .count();
+                orders[index] = if ties > 1 { highest + 1 } else { highest };
+            }
+        }
+        orders
+    }
+
+    /// number of bonds in every branch, a chain between
+    /// seeds, tips and branch points
+    pub fn branch_lengths(&self, count: usize) -> Vec<u32> {
+        let children = self.children(count);
+        let mut lengths = Vec::new();
+        for (index, own_children) in children.iter().enumerate() {
+            // branches start at seeds and branching particles
+            if self.parent(index).is_some() && own_children.len() == 1 {
+                continue;
+            }
+            for &first in own_children {
+                let (mut length, mut current) = (1, first);
+                while children[current].len() == 1 {
+                    current = children[current][0];
+                    length += 1;
+                }
+                lengths.push(length);
+            }
+        }
+        lengths
+    }
+}
+
+/// Crystal morphology numbers
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct BranchStatistics {
+    /// particles with one busy port
+    pub tips: u32,
+    /// particles with two busy ports
+    pub interior: u32,
+    /// particles with three or more busy ports
+    pub branch_points: u32,
+    /// highest Horton-Strahler order
+    pub max_strahler_order: u32,
+    // number of particles by count of busy ports
+    port_histogram: Vec<u32>,
+    // number of branches by length in bonds (index 0 is unused)
+    branch_lengths: Vec<u32>,
+    // number of streams (chains of equal order) by Strahler order (index 0 is order 1)
+    strahler_streams: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl BranchStatistics {
+    pub fn port_histogram(&self) -> Vec<u32> {
+        self.port_histogram.clone()
+    }
+    pub fn branch_lengths(&self) -> Vec<u32> {
+        self.branch_lengths.clone()
+    }
+    pub fn strahler_streams(&self) -> Vec<u32> {
+        self.strahler_streams.clone()
+    }
+
+    pub fn tip_fraction(&self) -> f64 {
+        self.fraction(self.tips)
+    }
+    pub fn interior_fraction(&self) -> f64 {
+        self.fraction(self.interior)
+    }
+    pub fn branch_point_fraction(&self) -> f64 {
+        self.fraction(self.branch_points)
+    }
+
+    /// all statistics as CSV rows of `metric,key,value`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("metric,key,value\n");
+        let mut row = |metric: &str, key: &dyn Display, value: &dyn Display| {
+            // writing to a String does not fail
+            let _ = writeln!(csv, "{},{},{}", metric, key, value);
+        };
+        row("particles", &"all", &self.particles());
+        for (ports, count) in self.port_histogram.iter().enumerate() {
+            row("busy_ports", &ports, count);
+        }
+        for &(kind, count) in &[
+            ("tip", self.tips),
+            ("interior", self.interior),
+            ("branch_point", self.branch_points),
+        ] {
+            row("particle_kind", &kind, &count);
+            row("particle_fraction", &kind, &self.fraction(count));
+        }
+        for (length, count) in self.branch_lengths.iter().enumerate().skip(1) {
+            row("branch_length", &length, count);
+        }
+        for (order, count) in self.strahler_streams.iter().enumerate() {
+            row("strahler_streams", &(order + 1), count);
+        }
+        row("max_strahler_order", &"all", &self.max_strahler_order);
+        csv
+    }
+}
+
+impl BranchStatistics {
+    /// statistics of particles with given busy port masks and their bond tree
+    pub fn new(busy_ports: &[u8], tree: &BondTree) -> Self {
+        let count = busy_ports.len();
+        let mut stats = BranchStatistics {
+            port_histogram: vec![0; PORT_BINS],
+            ..Self::default()
+        };
+        for ports in busy_ports.iter().map(|ports| ports.count_ones()) {
+            stats.port_histogram[ports as usize] += 1;
+            match ports {
+                0 => (),
+                1 => stats.tips += 1,
+                2 => stats.interior += 1,
+                _ => stats.branch_points += 1,
+            }
+        }
+
+        for length in tree.branch_lengths(count) {
+            let length = length as usize;
+            if stats.branch_lengths.len() <= length {
+                stats.branch_lengths.resize(length + 1, 0);
+            }
+            stats.branch_lengths[length] += 1;
+        }
+
+        let orders = tree.strahler_orders(count);
+        for (index, &order) in orders.iter().enumerate() {
+            // a stream ends where its parent has a higher order
+            let ends_here = tree
+                .parent(index)
+                .filter(|&parent| parent < count)
+                .map(|parent| orders[parent] != order)
+                .unwrap_or(true);
+            if ends_here {
+                let slot = order as usize - 1;
+                if stats.strahler_streams.len() <= slot {
+                    stats.strahler_streams.resize(slot + 1, 0);
+                }
+                stats.strahler_streams[slot] += 1;
+            }
+            stats.max_strahler_order = stats.max_strahler_order.max(order);
+        }
+        stats
+    }
+
+    fn particles(&self) -> u32 {
+        self.port_histogram.iter().sum()
+    }
+    fn fraction(&self, count: u32) -> f64 {
+        count as f64 / self.particles().max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn branch_statistics() {
+        // 0 - 1 - 2 < 3 - 4
+        //             5 - 6
+        //               \ 7
+        let mut tree = BondTree::new();
+        for &(index, parent) in &[(0, None), (1, Some(0)), (2, Some(1)), (3, Some(2))] {
+            tree.record(index, parent);
+        }
+        for &(index, parent) in &[(4, 3), (5, 2), (6, 5), (7, 5)] {
+            tree.record(index, Some(parent));
+        }
+        assert_eq!(tree.generation(7), 4);
+        assert_eq!(tree.strahler_orders(8), vec![2, 2, 2, 1, 1, 2, 1, 1]);
+        let mut lengths = tree.branch_lengths(8);
+        lengths.sort_unstable();
+        assert_eq!(lengths, vec![1, 1, 1, 2, 2]);
+
+        let busy_ports = [0b1, 0b11, 0b111, 0b11, 0b1, 0b111, 0b1, 0b1];
+        let stats = BranchStatistics::new(&busy_ports, &tree);
+        assert_eq!((stats.tips, stats.interior, stats.branch_points), (4, 2, 2));
+        assert_eq!(stats.tip_fraction(), 0.5);
+        assert_eq!(stats.branch_lengths(), vec![0, 3, 2]);
+        assert_eq!(stats.strahler_streams(), vec![3, 1]);
+        assert_eq!(stats.max_strahler_order, 2);
+
+        let csv = stats.to_csv();
+        assert!(csv.starts_with("metric,key,value\nparticles,all,8\nbusy_ports,0,0\n"));
+        assert!(csv.contains("\nbranch_length,2,2\n"));
+        assert!(csv.ends_with("max_strahler_order,all,2\n"));
+    }
+}
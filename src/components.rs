@@ -0,0 +1,170 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use wasm_bindgen::prelude::*;
+
+use crate::vector::Vector;
+
+/// Which static particles belong to the same crystal
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum Connectivity {
+    /// particles bound to each other, every seed grows its own crystal
+    Bonds,
+    /// particles within binding radius, touching crystals merge
+    Proximity,
+}
+
+/// Disjoint set forest with path halving and union by size
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(count: usize) -> Self {
+        Self {
+            parents: (0..count).collect(),
+            sizes: vec![1; count],
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+}
+
+/// Connected components of static particles
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct Components {
+    // component id of every particle, numbered in order of first particle
+    labels: Vec<u32>,
+    // number of particles in every component
+    sizes: Vec<u32>,
+    // [x, y] of every component
+    centroids: Vec<f64>,
+    // [min.x, min.y, max.x, max.y] of every component
+    bounding_boxes: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Components {
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+    pub fn labels(&self) -> Vec<u32> {
+        self.labels.clone()
+    }
+    pub fn sizes(&self) -> Vec<u32> {
+        self.sizes.clone()
+    }
+    pub fn centroids(&self) -> Vec<f64> {
+        self.centroids.clone()
+    }
+    pub fn bounding_boxes(&self) -> Vec<f64> {
+        self.bounding_boxes.clone()
+    }
+}
+
+impl Components {
+    /// components of points connected by edges (pairs of point indices)
+    pub fn new<I>(points: &[Vector], edges: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let mut sets = UnionFind::new(points.len());
+        for (a, b) in edges {
+            if a < points.len() && b < points.len() {
+                sets.union(a, b);
+            }
+        }
+
+        let mut components = Components::default();
+        let mut root_labels = vec![None; points.len()];
+        for (index, p) in points.iter().enumerate() {
+            let root = sets.find(index);
+            let label = *root_labels[root].get_or_insert_with(|| {
+                components.sizes.push(0);
+                components.centroids.extend_from_slice(&[0., 0.]);
+                components
+                    .bounding_boxes
+                    .extend_from_slice(&[p.x, p.y, p.x, p.y]);
+                components.sizes.len() - 1
+            });
+            components.labels.push(label as u32);
+            components.sizes[label] += 1;
+            components.centroids[2 * label] += p.x;
+            components.centroids[2 * label + 1] += p.y;
+            let bounds = &mut components.bounding_boxes[4 * label..4 * label + 4];
+            bounds[0] = bounds[0].min(p.x);
+            bounds[1] = bounds[1].min(p.y);
+            bounds[2] = bounds[2].max(p.x);
+            bounds[3] = bounds[3].max(p.y);
+        }
+        for (label, &size) in components.sizes.iter().enumerate() {
+            components.centroids[2 * label] /= size as f64;
+            components.centroids[2 * label + 1] /= size as f64;
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn labelling() {
+        let points = [
+            Vector::new(0., 0.),
+            Vector::new(10., 0.),
+            Vector::new(2., 2.),
+            Vector::new(12., -2.),
+            Vector::new(4., 0.),
+            Vector::new(50., 50.),
+        ];
+        let components = Components::new(&points, vec![(0, 2), (4, 2), (3, 1), (1, 9)]);
+        assert_eq!(components.count(), 3);
+        assert_eq!(components.labels(), vec![0, 1, 0, 1, 0, 2]);
+        assert_eq!(components.sizes(), vec![3, 2, 1]);
+        assert_eq!(
+            components.centroids(),
+            vec![2., 2. / 3., 11., -1., 50., 50.]
+        );
+        assert_eq!(
+            components.bounding_boxes()[..8],
+            [0., 0., 4., 2., 10., -2., 12., 0.]
+        );
+    }
+}
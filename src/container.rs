@@ -14,8 +14,10 @@
    limitations under the License.
 */
 
+use crate::geometry::Obstacle;
 use crate::particle::{MovingParticle, Positionable, StaticParticle};
 use crate::vector::Vector;
+use std::collections::BTreeMap;
 use std::slice;
 
 /// Reference to a particle of a certain type
@@ -88,6 +90,26 @@ impl Binnery {
         self.bins[bin].push(what);
     }
 
+    /// all bins overlapping a box between two corners
+    pub fn bins_in_box(&self, min: &Vector, max: &Vector) -> Vec<usize> {
+        let first = self.index(min);
+        let last = self.index(max);
+        let mut bins = Vec::new();
+        for y in first / BIN_DIMENSIONS..=last / BIN_DIMENSIONS {
+            for x in first % BIN_DIMENSIONS..=last % BIN_DIMENSIONS {
+                bins.push(x + y * BIN_DIMENSIONS);
+            }
+        }
+        bins
+    }
+
+    /// add to every bin overlapping a box between two corners
+    pub fn add_box(&mut self, what: usize, min: &Vector, max: &Vector) {
+        for bin in self.bins_in_box(min, max) {
+            self.bins[bin].push(what);
+        }
+    }
+
     pub fn remove(&mut self, what: usize, pos: &Vector) {
         let bin = self.index(pos);
         self.bins[bin].retain(|&x| x != what);
@@ -118,6 +140,8 @@ where
 {
     particles: Vec<T>,
     bins: Binnery,
+    // field period for periodic boundary, None for bounded field
+    period: Option<Vector>,
 }
 
 pub type MovingParticleContainer = ParticleContainer<MovingParticle>;
@@ -166,6 +190,34 @@ where
         Self {
             particles: Vec::with_capacity(capacity),
             bins: Binnery::new(field_dimensions),
+            period: None,
+        }
+    }
+
+    /// Set field period (full width and height) to make neighbour queries
+    /// wrap around field edges, or None for a bounded field
+    pub fn set_period(&mut self, period: Option<Vector>) {
+        self.period = period;
+    }
+
+    /// Positions equivalent to target in a periodic field, which are close enough
+    /// to the binned area to have neighbours within range
+    fn periodic_images(&self, target: &Vector, range: f64) -> Vec<Vector> {
+        match self.period {
+            None => vec![*target],
+            Some(period) => {
+                let extent = self.bins.offset + range;
+                let mut images = Vec::with_capacity(4);
+                for &dy in &[0., -period.y, period.y] {
+                    for &dx in &[0., -period.x, period.x] {
+                        let image = Vector::new(target.x + dx, target.y + dy);
+                        if image.x.abs() <= extent && image.y.abs() <= extent {
+                            images.push(image);
+                        }
+                    }
+                }
+                images
+            }
         }
     }
 
@@ -198,19 +250,48 @@ where
         }
     }
 
-    /// Apply an operator to particles
+    /// Apply an operator to particles, along with their indices
     pub fn apply<F>(&mut self, operator: F)
     where
-        F: std::ops::Fn(&mut T) -> (),
+        F: std::ops::Fn(usize, &mut T),
     {
         self.bins.clear();
-        self.particles.iter_mut().for_each(operator);
+        for (index, particle) in self.particles.iter_mut().enumerate() {
+            operator(index, particle);
+        }
         for i in 0..self.particles.len() {
             let particle = &self.particles[i];
             self.bins.add(i, &particle.position());
         }
     }
 
+    /// Select particles within a square of half-size range around target
+    /// In a periodic field, particles across field edges are included as well;
+    /// their positions are not wrapped, use minimum image distance to compare
+    pub fn select_nearby(&self, target: &Vector, range: f64) -> Vec<ParticleRef<'_, T>> {
+        let mut indices = Vec::with_capacity(16);
+        for image in self.periodic_images(target, range) {
+            for bin in self.bins.bins_in_box(
+                &Vector::new(image.x - range, image.y - range),
+                &Vector::new(image.x + range, image.y + range),
+            ) {
+                indices.extend(self.bins.bins[bin].iter().copied().filter(|&index| {
+                    let pos = self.particles[index].position();
+                    (pos.x - image.x).abs() <= range && (pos.y - image.y).abs() <= range
+                }));
+            }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|index| ParticleRef {
+                index,
+                particle: &self.particles[index],
+            })
+            .collect()
+    }
+
     /// Get a reference to particle at an index
     pub fn at(&self, index: usize) -> Option<&T> {
         if index < self.particles.len() {
@@ -255,6 +336,9 @@ where
     pub fn as_ptr(&self) -> *const T {
         self.particles.as_ptr()
     }
+    pub fn as_slice(&self) -> &[T] {
+        &self.particles
+    }
 
     /// Update a particle (from an copied reference)
     pub fn update(&mut self, particle: &Particle<T>) {
@@ -262,25 +346,53 @@ where
             self.particles[particle.index] = particle.particle
         }
     }
+
+    /// Update a particle (from a copied reference) that may have changed its position
+    pub fn relocate(&mut self, particle: &Particle<T>) {
+        if particle.index < self.particles.len() {
+            self.bins
+                .remove(particle.index, &self.particles[particle.index].position());
+            self.bins.add(particle.index, &particle.particle.position());
+            self.particles[particle.index] = particle.particle
+        }
+    }
+}
+
+impl MovingParticleContainer {
+    /// Indices of particles in every drifting cluster, by cluster id
+    /// (free particles are not included)
+    pub fn group_by_cluster_id(&self) -> BTreeMap<u32, Vec<usize>> {
+        let mut clusters = BTreeMap::<u32, Vec<usize>>::new();
+        for moving in self.values() {
+            let cluster = moving.particle.cluster();
+            if cluster != 0 {
+                clusters.entry(cluster).or_default().push(moving.index);
+            }
+        }
+        clusters
+    }
 }
 
 impl StaticParticleContainer {
-    pub fn select_nearby_clusters(&self, target: &Vector, _range: f64) -> Vec<usize> {
+    pub fn select_nearby_clusters(&self, target: &Vector, range: f64) -> Vec<usize> {
         // choose most neightbouring bins; this puts restrictions on minimum bin size
-        let this_index = self.bins.index(target);
-        let this_y = this_index / BIN_DIMENSIONS;
-        let this_x = this_index % BIN_DIMENSIONS;
-        let mut bins = vec![
-            Binnery::to_linear(this_x.saturating_sub(1), this_y.saturating_sub(1)),
-            Binnery::to_linear(this_x, this_y.saturating_sub(1)),
-            Binnery::to_linear(this_x + 1, this_y.saturating_sub(1)),
-            Binnery::to_linear(this_x.saturating_sub(1), this_y),
-            Binnery::to_linear(this_x, this_y),
-            Binnery::to_linear(this_x + 1, this_y),
-            Binnery::to_linear(this_x.saturating_sub(1), this_y + 1),
-            Binnery::to_linear(this_x, this_y + 1),
-            Binnery::to_linear(this_x + 1, this_y + 1),
-        ];
+        let mut bins = Vec::with_capacity(9);
+        for image in self.periodic_images(target, range) {
+            let this_index = self.bins.index(&image);
+            let this_y = this_index / BIN_DIMENSIONS;
+            let this_x = this_index % BIN_DIMENSIONS;
+            bins.extend_from_slice(&[
+                Binnery::to_linear(this_x.saturating_sub(1), this_y.saturating_sub(1)),
+                Binnery::to_linear(this_x, this_y.saturating_sub(1)),
+                Binnery::to_linear(this_x + 1, this_y.saturating_sub(1)),
+                Binnery::to_linear(this_x.saturating_sub(1), this_y),
+                Binnery::to_linear(this_x, this_y),
+                Binnery::to_linear(this_x + 1, this_y),
+                Binnery::to_linear(this_x.saturating_sub(1), this_y + 1),
+                Binnery::to_linear(this_x, this_y + 1),
+                Binnery::to_linear(this_x + 1, this_y + 1),
+            ]);
+        }
         bins.sort_unstable();
         bins.dedup();
         bins
@@ -312,41 +424,101 @@ impl StaticParticleContainer {
             })
     }
     /// Select potential targets
+    /// In a periodic field, targets across field edges are included as well;
+    /// their positions are not wrapped, use minimum image distance to compare
     pub fn select_for_binding(
         &self,
         target: &Vector,
         range: f64,
     ) -> Vec<ParticleRef<StaticParticle>> {
-        // choose most neightbouring bins; this puts restrictions on minimum bin size
-        let mut bins = vec![
-            self.bins
-                .index(&Vector::new(target.x - range, target.y - range)),
-            self.bins
-                .index(&Vector::new(target.x - range, target.y + range)),
-            self.bins
-                .index(&Vector::new(target.x + range, target.y - range)),
-            self.bins
-                .index(&Vector::new(target.x + range, target.y + range)),
-        ];
-        bins.sort_unstable();
-        bins.dedup();
-
-        // convert indices from bins into particle refs and return final set of potential targets
-        bins.into_iter()
-            .fold(Vec::with_capacity(16), |mut accum, bin| {
+        let mut selected = Vec::with_capacity(16);
+        for image in self.periodic_images(target, range) {
+            // choose most neightbouring bins; this puts restrictions on minimum bin size
+            let mut bins = vec![
+                self.bins
+                    .index(&Vector::new(image.x - range, image.y - range)),
+                self.bins
+                    .index(&Vector::new(image.x - range, image.y + range)),
+                self.bins
+                    .index(&Vector::new(image.x + range, image.y - range)),
+                self.bins
+                    .index(&Vector::new(image.x + range, image.y + range)),
+            ];
+            bins.sort_unstable();
+            bins.dedup();
+
+            // convert indices from bins into particle refs and return final set of potential targets
+            for bin in bins {
                 self.bins.get_bin(bin).into_iter().for_each(|index| {
                     let particle = &self.particles[index];
-                    if (particle.pos.x - target.x).abs() <= range
-                        && (particle.pos.y - target.y).abs() <= range
+                    if (particle.pos.x - image.x).abs() <= range
+                        && (particle.pos.y - image.y).abs() <= range
+                        && selected
+                            .iter()
+                            .all(|other: &ParticleRef<_>| other.index != index)
                     {
-                        accum.push(ParticleRef {
+                        selected.push(ParticleRef {
                             index,
                             particle: &self.particles[index],
                         })
                     }
                 });
-                accum
-            })
+            }
+        }
+        selected
+    }
+}
+
+/// Container for obstacles, binned by their bounding boxes
+pub struct ObstacleContainer {
+    obstacles: Vec<Obstacle>,
+    bins: Binnery,
+}
+
+impl ObstacleContainer {
+    /// Create an empty container
+    pub fn new(field_dimensions: &Vector) -> Self {
+        Self {
+            obstacles: Vec::new(),
+            bins: Binnery::new(field_dimensions),
+        }
+    }
+
+    /// Return current number of obstacles
+    pub fn size(&self) -> usize {
+        self.obstacles.len()
+    }
+
+    /// Add a new obstacle to the container
+    pub fn add_obstacle(&mut self, obstacle: Obstacle) {
+        let (min, max) = obstacle.bounding_box();
+        self.bins.add_box(self.obstacles.len(), &min, &max);
+        self.obstacles.push(obstacle);
+    }
+
+    /// Remove all obstacles
+    pub fn clear(&mut self) {
+        self.obstacles.clear();
+        self.bins.clear();
+    }
+
+    /// Select obstacles that may be hit within range around target
+    pub fn select_for_collision(&self, target: &Vector, range: f64) -> Vec<&Obstacle> {
+        let mut indices = self
+            .bins
+            .bins_in_box(
+                &Vector::new(target.x - range, target.y - range),
+                &Vector::new(target.x + range, target.y + range),
+            )
+            .into_iter()
+            .flat_map(|bin| self.bins.bins[bin].iter().copied())
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|index| &self.obstacles[index])
+            .collect()
     }
 }
 
@@ -400,4 +572,95 @@ mod tests {
             "Bins at end"
         );
     }
+
+    #[wasm_bindgen_test]
+    fn nearby_selection() {
+        let mut container = MovingParticleContainer::new(10, &Vector::new(200., 200.));
+        for &(x, y) in &[(0., 0.), (30., 0.), (0., -40.), (195., 0.)] {
+            container.add_particle(MovingParticle {
+                pos: Vector::new(x, y),
+                ..MovingParticle::default()
+            });
+        }
+        let indices = |selected: Vec<ParticleRef<MovingParticle>>| {
+            selected.iter().map(|p| p.index).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            indices(container.select_nearby(&Vector::new(5., 0.), 30.)),
+            vec![0, 1]
+        );
+        assert_eq!(
+            indices(container.select_nearby(&Vector::new(0., 0.), 40.)),
+            vec![0, 1, 2]
+        );
+        assert!(container
+            .select_nearby(&Vector::new(-195., 0.), 10.)
+            .is_empty());
+
+        // particle at x = 195 is 10 units away from x = -195 across the field edge
+        container.set_period(Some(Vector::new(400., 400.)));
+        assert_eq!(
+            indices(container.select_nearby(&Vector::new(-195., 0.), 10.)),
+            vec![3]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn obstacle_selection() {
+        let mut obstacles = ObstacleContainer::new(&Vector::new(200., 200.));
+        obstacles.add_obstacle(Obstacle::Segment(
+            Vector::new(-100., 0.),
+            Vector::new(100., 0.),
+        ));
+        obstacles.add_obstacle(Obstacle::Circle {
+            center: Vector::new(150., 150.),
+            radius: 10.,
+        });
+        assert_eq!(
+            obstacles
+                .select_for_collision(&Vector::new(0., 5.), 5.)
+                .len(),
+            1
+        );
+        assert_eq!(
+            obstacles
+                .select_for_collision(&Vector::new(-90., -0.5), 1.)
+                .len(),
+            1
+        );
+        assert!(obstacles
+            .select_for_collision(&Vector::new(0., -100.), 5.)
+            .is_empty());
+        assert_eq!(
+            obstacles
+                .select_for_collision(&Vector::new(120., 120.), 200.)
+                .len(),
+            2
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn periodic_selection() {
+        let mut st = StaticParticleContainer::new(10, &Vector::new(100., 100.));
+        st.add_particle(StaticParticle {
+            pos: Vector::new(99., 0.),
+            ..StaticParticle::default()
+        });
+        st.add_particle(StaticParticle {
+            pos: Vector::new(-99., -99.),
+            ..StaticParticle::default()
+        });
+        assert!(st.select_for_binding(&Vector::new(-98., 0.), 5.).is_empty());
+
+        st.set_period(Some(Vector::new(200., 200.)));
+        let across = st.select_for_binding(&Vector::new(-98., 0.), 5.);
+        assert_eq!(across.len(), 1);
+        assert_eq!(across[0].index, 0);
+        let corner = st.select_for_binding(&Vector::new(98., 98.), 5.);
+        assert_eq!(corner.len(), 1);
+        assert_eq!(corner[0].index, 1);
+        assert!(st
+            .select_nearby_clusters(&Vector::new(-99., 0.), 5.)
+            .contains(&st.bins.index(&Vector::new(99., 0.))));
+    }
 }
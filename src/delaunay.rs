@@ -0,0 +1,402 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+use crate::extfn;
+use crate::geometry;
+use crate::hull;
+use crate::vector::Vector;
+
+/// Number of ghost points around the field, they bound Voronoi cells of outer particles
+const GHOST_POINTS: usize = 8;
+
+/// Triangle with a cached circumcircle
+#[derive(Copy, Clone)]
+struct Triangle {
+    // vertex indices, counter-clockwise
+    vertices: [usize; 3],
+    center: Vector,
+    radius_sq: f64,
+}
+
+impl Triangle {
+    fn new(vertices: [usize; 3], points: &[Vector]) -> Self {
+        let [a, b, c] = [
+            points[vertices[0]],
+            points[vertices[1]],
+            points[vertices[2]],
+        ];
+        let d = 2. * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+        if d == 0. {
+            // collinear vertices: the circle is a half-plane, treat it as containing everything
+            return Self {
+                vertices,
+                center: a,
+                radius_sq: f64::INFINITY,
+            };
+        }
+        let (a2, b2, c2) = (
+            Vector::dot(&a, &a),
+            Vector::dot(&b, &b),
+            Vector::dot(&c, &c),
+        );
+        let center = Vector::new(
+            (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+            (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+        );
+        Self {
+            vertices,
+            center,
+            radius_sq: Vector::distance_squared(&center, &a),
+        }
+    }
+
+    /// collinear vertices have no circumcentre to be a Voronoi vertex
+    fn is_degenerate(&self) -> bool {
+        self.radius_sq.is_infinite()
+    }
+
+    fn circle_contains(&self, p: &Vector) -> bool {
+        Vector::distance_squared(&self.center, p) < self.radius_sq
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Delaunay triangulation (Bowyer-Watson), triangles counter-clockwise.
+/// Duplicate points are left out
+fn triangulate(points: &[Vector]) -> Vec<Triangle> {
+    let count = points.len();
+    if count < 3 {
+        return Vec::new();
+    }
+    // super triangle enclosing every point far away
+    let reach = points
+        .iter()
+        .fold(1f64, |reach, p| reach.max(p.x.abs()).max(p.y.abs()))
+        * 100.;
+    let mut all = points.to_vec();
+    all.extend_from_slice(&[
+        Vector::new(-3. * reach, -3. * reach),
+        Vector::new(3. * reach, -3. * reach),
+        Vector::new(0., 3. * reach),
+    ]);
+    let mut triangles = vec![Triangle::new([count, count + 1, count + 2], &all)];
+
+    for (index, p) in points.iter().enumerate() {
+        let mut bad = Vec::new();
+        let mut next = 0;
+        while next < triangles.len() {
+            if triangles[next].circle_contains(p) {
+                bad.push(triangles.swap_remove(next));
+            } else {
+                next += 1;
+            }
+        }
+        // edges of the cavity are the ones not shared by two removed triangles
+        let mut shared = HashMap::<(usize, usize), u32>::new();
+        for (a, b) in bad.iter().flat_map(|triangle| triangle.edges()) {
+            *shared.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+        for (a, b) in bad.iter().flat_map(|triangle| triangle.edges()) {
+            if shared[&(a.min(b), a.max(b))] == 1 {
+                triangles.push(Triangle::new([a, b, index], &all));
+            }
+        }
+    }
+    triangles.retain(|triangle| triangle.vertices.iter().all(|&v| v < count));
+    triangles
+}
+
+/// Part of a polygon inside a convex counter-clockwise polygon (Sutherland-Hodgman)
+fn clip_to_convex(subject: &[Vector], clip: &[Vector]) -> Vec<Vector> {
+    let mut result = subject.to_vec();
+    for (a, b) in geometry::polygon_edges(clip) {
+        let side = |p: &Vector| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+        let input = std::mem::take(&mut result);
+        for (p, q) in geometry::polygon_edges(&input) {
+            let (sp, sq) = (side(&p), side(&q));
+            if sp >= 0. {
+                result.push(p);
+            }
+            if (sp >= 0.) != (sq >= 0.) {
+                result.push(p + (q - p) * (sp / (sp - sq)));
+            }
+        }
+    }
+    result
+}
+
+/// Exact neighbours of static particles: Delaunay triangles, Voronoi cells
+/// and nearest neighbour distances
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct Tessellation {
+    /// average distance to the nearest neighbour
+    pub mean_nearest_distance: f64,
+    /// standard deviation of distance to the nearest neighbour
+    pub std_nearest_distance: f64,
+    /// average number of Delaunay neighbours
+    pub mean_neighbours: f64,
+    // vertex indices, 3 per triangle
+    triangles: Vec<u32>,
+    // Voronoi cell of every particle, vertex count followed by x and y of every vertex
+    cells: Vec<f64>,
+    // distance to the nearest neighbour of every particle, 0 for coincident
+    // particles and for a particle without neighbours
+    nearest_distances: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Tessellation {
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+    /// one convex cell per particle, clipped to the convex outline given on
+    /// construction (so cells of an annulus field cover its hole too);
+    /// vertex count followed by x and y of every vertex
+    pub fn voronoi_cells(&self) -> Vec<f64> {
+        self.cells.clone()
+    }
+    /// 0 for coincident particles and for a particle without neighbours
+    pub fn nearest_distances(&self) -> Vec<f64> {
+        self.nearest_distances.clone()
+    }
+}
+
+impl Tessellation {
+    /// triangulate points and clip their Voronoi cells to an outline
+    /// (counter-clockwise polygon around the field centre)
+    pub fn new(points: &[Vector], outline: &[Vector]) -> Self {
+        let count = points.len();
+        // ghost points far outside the outline close the cells of outer particles;
+        // points within (ghost radius - particle radius) / 2 from the centre
+        // are closer to some particle than to any ghost
+        let reach = points
+            .iter()
+            .chain(outline.iter())
+            .map(Vector::length)
+            .fold(1f64, f64::max);
+        let mut all = points.to_vec();
+        all.extend((0..GHOST_POINTS).map(|i| {
+            let (sin, cos) = (2. * PI * i as f64 / GHOST_POINTS as f64).sin_cos();
+            Vector::new(cos, sin) * (4. * reach)
+        }));
+        let triangles = triangulate(&all);
+
+        let mut stats = Tessellation::default();
+        let mut incident = vec![Vec::new(); count];
+        let mut edges = HashSet::<(usize, usize)>::new();
+        for triangle in &triangles {
+            let real = triangle.vertices.iter().all(|&v| v < count);
+            if real {
+                stats
+                    .triangles
+                    .extend(triangle.vertices.iter().map(|&v| v as u32));
+            }
+            if !triangle.is_degenerate() {
+                for &v in triangle.vertices.iter().filter(|&&v| v < count) {
+                    incident[v].push(triangle.center);
+                }
+            }
+            for (a, b) in triangle.edges().iter().copied() {
+                if a < count && b < count {
+                    edges.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+
+        // cells are convex, their vertices go around the particle
+        let cells = incident
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut centers)| {
+                let p = points[index];
+                let angle = |c: &Vector| extfn::atan2(c.y - p.y, c.x - p.x);
+                centers.sort_by(|a, b| {
+                    angle(a)
+                        .partial_cmp(&angle(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                if centers.len() < 3 {
+                    Vec::new()
+                } else {
+                    clip_to_convex(outline, &centers)
+                }
+            })
+            .collect::<Vec<_>>();
+        stats.cells = hull::flatten_outlines(&cells);
+
+        let mut nearest = vec![f64::INFINITY; count];
+        let mut neighbours = vec![0u32; count];
+        for &(a, b) in &edges {
+            let distance = Vector::length(&Vector::diff(&points[a], &points[b]));
+            nearest[a] = nearest[a].min(distance);
+            nearest[b] = nearest[b].min(distance);
+            neighbours[a] += 1;
+            neighbours[b] += 1;
+        }
+        // duplicates are left out of the triangulation, but are right next to each other
+        let mut first_at = HashMap::new();
+        for (index, p) in points.iter().enumerate() {
+            let first = *first_at
+                .entry((p.x.to_bits(), p.y.to_bits()))
+                .or_insert(index);
+            if first != index {
+                nearest[first] = 0.;
+                nearest[index] = 0.;
+            }
+        }
+        let measured = nearest
+            .iter()
+            .copied()
+            .filter(|distance| distance.is_finite())
+            .collect::<Vec<_>>();
+        if !measured.is_empty() {
+            let n = measured.len() as f64;
+            stats.mean_nearest_distance = measured.iter().sum::<f64>() / n;
+            stats.std_nearest_distance = (measured
+                .iter()
+                .map(|d| (d - stats.mean_nearest_distance).powi(2))
+                .sum::<f64>()
+                / n)
+                .sqrt();
+            stats.mean_neighbours = neighbours.iter().sum::<u32>() as f64 / count as f64;
+        }
+        stats.nearest_distances = nearest
+            .into_iter()
+            .map(|distance| if distance.is_finite() { distance } else { 0. })
+            .collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn voronoi_grid() {
+        // 3 x 3 grid, slightly perturbed to avoid cocircular points
+        let points = (0..9)
+            .map(|i| {
+                let (x, y) = ((i % 3) as f64 - 1., (i / 3) as f64 - 1.);
+                Vector::new(x * 10. + 0.01 * y, y * 10. + 0.02 * x)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(triangulate(&points).len(), 8);
+
+        let outline = [
+            Vector::new(-15., -15.),
+            Vector::new(15., -15.),
+            Vector::new(15., 15.),
+            Vector::new(-15., 15.),
+        ];
+        let tessellation = Tessellation::new(&points, &outline);
+        assert_eq!(tessellation.triangles().len(), 24);
+
+        let cells = tessellation.voronoi_cells();
+        let mut total_area = 0.;
+        let mut rest = &cells[..];
+        let mut areas = Vec::new();
+        while let Some((&n, tail)) = rest.split_first() {
+            let (cell, next) = tail.split_at(2 * n as usize);
+            let vertices = cell
+                .chunks(2)
+                .map(|v| Vector::new(v[0], v[1]))
+                .collect::<Vec<_>>();
+            areas.push(geometry::polygon_signed_area(&vertices));
+            total_area += areas.last().unwrap();
+            rest = next;
+        }
+        assert_eq!(areas.len(), 9);
+        assert!(
+            (total_area - 900.).abs() < 1e-6,
+            "Cells cover {}",
+            total_area
+        );
+        assert!((areas[4] - 100.).abs() < 0.5, "Central cell {}", areas[4]);
+
+        assert!((tessellation.mean_nearest_distance - 10.).abs() < 0.05);
+        assert!(tessellation.std_nearest_distance < 0.05);
+        assert!(tessellation.nearest_distances().iter().all(|&d| d < 10.1));
+    }
+
+    #[wasm_bindgen_test]
+    fn collinear_points() {
+        let outline = [
+            Vector::new(-15., -15.),
+            Vector::new(15., -15.),
+            Vector::new(15., 15.),
+            Vector::new(-15., 15.),
+        ];
+        let points = (0..5)
+            .map(|i| Vector::new(i as f64 * 5. - 10., 0.))
+            .collect::<Vec<_>>();
+        let collinear = Triangle::new([0, 1, 2], &points);
+        assert!(collinear.is_degenerate());
+        assert!(!Triangle::new([0, 1, 2], &[points[0], points[1], outline[2]]).is_degenerate());
+
+        // every cell is a strip across the field
+        let cells = Tessellation::new(&points, &outline).voronoi_cells();
+        assert!(cells.iter().all(|v| v.is_finite()));
+        let mut rest = &cells[..];
+        let mut total_area = 0.;
+        while let Some((&n, tail)) = rest.split_first() {
+            let (cell, next) = tail.split_at(2 * n as usize);
+            let vertices = cell
+                .chunks(2)
+                .map(|v| Vector::new(v[0], v[1]))
+                .collect::<Vec<_>>();
+            total_area += geometry::polygon_signed_area(&vertices);
+            rest = next;
+        }
+        assert!(
+            (total_area - 900.).abs() < 1e-6,
+            "Cells cover {}",
+            total_area
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn coincident_points() {
+        let outline = [
+            Vector::new(-15., -15.),
+            Vector::new(15., -15.),
+            Vector::new(15., 15.),
+            Vector::new(-15., 15.),
+        ];
+        let points = [
+            Vector::new(0., 0.),
+            Vector::new(5., 0.),
+            Vector::new(0., 6.),
+            Vector::new(5., 0.),
+        ];
+        let tessellation = Tessellation::new(&points, &outline);
+        assert_eq!(tessellation.nearest_distances(), [5., 0., 6., 0.]);
+        assert!((tessellation.mean_nearest_distance - 2.75).abs() < 1e-9);
+
+        let lone = Tessellation::new(&points[..1], &outline);
+        assert_eq!(lone.nearest_distances(), [0.]);
+    }
+}
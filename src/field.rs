@@ -14,22 +14,71 @@
    limitations under the License.
 */
 
+use js_sys::Float64Array;
+use std::cell::Cell;
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 use crate::extfn;
 
-use crate::container::{MovingParticleContainer, Particle, StaticParticleContainer};
-use crate::particle::{BindingConfiguration, BindingResult, MovingParticle, StaticParticle};
+use crate::alignment::AligningField;
+use crate::analysis::FractalAnalysis;
+use crate::boundary::{Boundary, BoundaryBehaviour, SpawnRegion};
+use crate::branches::{BondTree, BranchStatistics};
+use crate::components::{Components, Connectivity};
+use crate::container::{
+    MovingParticleContainer, ObstacleContainer, Particle, StaticParticleContainer,
+};
+use crate::delaunay::Tessellation;
+use crate::geometry::Obstacle;
+use crate::hull;
+use crate::layout;
+use crate::lifetime::{ExpiryAction, WanderStatistics};
+use crate::mask::Mask;
+use crate::particle::{
+    AttachmentAlignment, BindingConfiguration, BindingResult, BondSegment, MovingParticle,
+    ParticleFlag, StaticParticle,
+};
+use crate::profile::DensityProfile;
+use crate::repulsion::Repulsion;
+use crate::rng::Rng;
+use crate::seeds;
+use crate::species::{Mobility, Species, MAX_SPECIES};
+use crate::symmetry::Symmetry;
+use crate::telemetry::{
+    GrowthSample, Telemetry, PORT_HISTOGRAM_SIZE, SAMPLE_STRIDE, TELEMETRY_CAPACITY,
+};
 use crate::vector::*;
+use crate::walk::{self, WalkModel};
 
 const MAX_MOVING: usize = 1000;
 const MAX_STATIC: usize = 5000;
 
+/// How many times to retry a random spawn position that falls inside an obstacle
+const SPAWN_ATTEMPTS: usize = 32;
+
 /// Particles are affected by field force; e.g. centripetal force
 /// to prevent them from scattering away.
 /// Field force, different at every point, is multiplied by this constant
+/// (default attractor strength)
 const VELOCITY_FIELD_ATTENUATION: f64 = 1.0;
 
+/// How far outside an obstacle surface a particle caught inside is put
+const OBSTACLE_CLEARANCE: f64 = 1e-6;
+
+/// Height of the top spawn band, relative to full field height
+const TOP_SPAWN_BAND: f64 = 0.05;
+
+/// Number of vertices approximating curved field boundaries in Voronoi cells
+const BOUNDARY_OUTLINE_SEGMENTS: usize = 64;
+
+/// Drifting clusters of N particles move N^-exponent times as fast as free particles
+/// (default exponent)
+const CLUSTER_MOBILITY_EXPONENT: f64 = 0.5;
+
+/// Relative slack for cluster members touching static particles at binding distance
+const CLUSTER_CONTACT_TOLERANCE: f64 = 1e-6;
+
 /// Result for attachment possibility check
 /// To implement a custom starting static particle placement, we should be able
 /// to arbitrarily position new static particles. However, to maintain a grid,
@@ -56,45 +105,69 @@ pub struct Field {
     mp_container: MovingParticleContainer,
     // static particles
     sp_container: StaticParticleContainer,
+    // which static particle every static particle is bound to
+    bond_tree: BondTree,
+    // bonds between static particles, for rendering
+    bonds: Vec<BondSegment>,
     // binding configurations
     bind_cfgs: [BindingConfiguration; 1],
     // field dimensions, from -dim to +dim
     dimensions: Vector,
+    // field shape, within dimensions
+    boundary: Boundary,
+    // what happens to moving particles crossing the boundary
+    boundary_behaviour: BoundaryBehaviour,
+    // where boundary particles are spawned
+    spawn_region: SpawnRegion,
+    // constant drift added to every moving particle direction
+    gravity: Vector,
+    // multiplier for the center attractor force
+    attractor_strength: f64,
+    // motion parameters per moving particle species
+    species: [Species; MAX_SPECIES],
+    // current engine time, to compute particle age
+    time: f64,
+    // external field rotating moving particles
+    aligning_field: Option<AligningField>,
+    // moving particle lifetime, unlimited if not set
+    ttl: Option<f64>,
+    // what happens to moving particles after their lifetime
+    expiry_action: ExpiryAction,
+    // wander times of attached particles
+    wander_stats: WanderStatistics,
+    // growth metrics of recent ticks
+    telemetry: Telemetry,
+    // interaction between moving particles
+    repulsion: Repulsion,
+    // moving particles bond to each other into drifting clusters
+    aggregation: bool,
+    // how fast cluster mobility falls with cluster size
+    cluster_mobility_exponent: f64,
+    // id for the next drifting cluster
+    next_cluster_id: u32,
+    // static shapes moving particles bounce off
+    obstacles: ObstacleContainer,
+    // bitmap regions moving particles cannot enter and crystals cannot grow into
+    forbidden_mask: Option<Mask>,
+    // bitmap spawn density weights
+    spawn_mask: Option<Mask>,
+    // symmetry group replicating every new static particle
+    symmetry: Option<Symmetry>,
+    // seeded random generator for reproducible seed placement
+    rng: Rng,
     // number of used particles
 }
 
 #[wasm_bindgen]
 impl Field {
-    /// generate random position in the field (elliptical field)
-    fn random_pos_in_field(dimensions: &Vector) -> Vector {
-        loop {
-            let v = Vector {
-                x: (extfn::random() * 2. - 1.) * dimensions.x,
-                y: (extfn::random() * 2. - 1.) * dimensions.y,
-            };
-            if v.x * v.x / (dimensions.x * dimensions.x) + v.y * v.y / (dimensions.y * dimensions.y)
-                <= 1.
-            {
-                return v;
-            }
-        }
-    }
-    /// generate random position on the field boundary (elliptical field)
-    fn random_boundary_pos_in_field(dimensions: &Vector) -> Vector {
-        // this is slightly wrong, because densities will skew in a truly elliptical field
-        let mut v = Field::random_vel_in_field();
-        v.x *= dimensions.x;
-        v.y *= dimensions.y;
-        v
-    }
-
     /// generate random velocity vector with length 1
     fn random_vel_in_field() -> Vector {
-        let theta_sc = (extfn::random() * 6.28).sin_cos();
-        Vector {
-            x: theta_sc.0,
-            y: theta_sc.1,
-        }
+        walk::random_direction()
+    }
+
+    /// generate random rotation, degrees
+    fn random_rot() -> f64 {
+        extfn::random() * 360.
     }
 
     #[wasm_bindgen(constructor)]
@@ -103,57 +176,805 @@ impl Field {
         Field {
             mp_container: MovingParticleContainer::new(MAX_MOVING, &dimensions),
             sp_container: StaticParticleContainer::new(MAX_STATIC, &dimensions),
+            bond_tree: BondTree::new(),
+            bonds: Vec::with_capacity(MAX_STATIC),
             bind_cfgs: [BindingConfiguration::make_hexa()],
             dimensions,
+            boundary: Boundary::Ellipse(dimensions),
+            boundary_behaviour: BoundaryBehaviour::Reflect,
+            spawn_region: SpawnRegion::Boundary,
+            gravity: Vector::new(0., 0.),
+            attractor_strength: VELOCITY_FIELD_ATTENUATION,
+            species: [Species::new(); MAX_SPECIES],
+            time: 0.,
+            aligning_field: None,
+            ttl: None,
+            expiry_action: ExpiryAction::Remove,
+            wander_stats: WanderStatistics::default(),
+            telemetry: Telemetry::new(TELEMETRY_CAPACITY),
+            repulsion: Repulsion::None,
+            aggregation: false,
+            cluster_mobility_exponent: CLUSTER_MOBILITY_EXPONENT,
+            next_cluster_id: 1,
+            obstacles: ObstacleContainer::new(&dimensions),
+            forbidden_mask: None,
+            spawn_mask: None,
+            symmetry: None,
+            rng: Rng::new((extfn::random() * (1u64 << 53) as f64) as u64),
+        }
+    }
+
+    /// elliptical field shape (default), half-axes should not exceed field dimensions
+    pub fn set_ellipse_boundary(&mut self, half_width: f64, half_height: f64) {
+        self.set_boundary(Boundary::Ellipse(Vector::new(half_width, half_height)));
+    }
+    /// rectangular field shape, should not exceed field dimensions
+    pub fn set_rectangle_boundary(&mut self, half_width: f64, half_height: f64) {
+        self.set_boundary(Boundary::Rectangle(Vector::new(half_width, half_height)));
+    }
+    /// polygonal field shape, vertices as flat [x0, y0, x1, y1, ...] list
+    pub fn set_polygon_boundary(&mut self, vertices: &[f64]) {
+        self.set_boundary(Boundary::Polygon(
+            vertices
+                .chunks_exact(2)
+                .map(|xy| Vector::new(xy[0], xy[1]))
+                .collect(),
+        ));
+    }
+    /// ring-shaped field between two circles around the center,
+    /// inner radius must be smaller than outer
+    pub fn set_annulus_boundary(&mut self, inner_radius: f64, outer_radius: f64) {
+        self.set_boundary(Boundary::Annulus {
+            inner: inner_radius,
+            outer: outer_radius,
+        });
+    }
+    /// shapes without area are ignored, the boundary stays as it was
+    fn set_boundary(&mut self, boundary: Boundary) {
+        if boundary.is_valid() {
+            self.boundary = boundary;
+            self.sync_period();
+        }
+    }
+    /// periodic behaviour also makes binding work across field edges;
+    /// it takes effect for rectangular fields only, others reflect
+    pub fn set_boundary_behaviour(&mut self, behaviour: BoundaryBehaviour) {
+        self.boundary_behaviour = behaviour;
+        self.sync_period();
+    }
+    /// behaviour in effect for the current boundary shape
+    fn boundary_behaviour(&self) -> BoundaryBehaviour {
+        match self.boundary_behaviour {
+            BoundaryBehaviour::Periodic if !self.boundary.is_periodic() => {
+                BoundaryBehaviour::Reflect
+            }
+            behaviour => behaviour,
+        }
+    }
+    /// where `add_boundary_particle` spawns particles
+    pub fn set_spawn_region(&mut self, region: SpawnRegion) {
+        self.spawn_region = region;
+    }
+    /// check if a position is inside the field boundary
+    pub fn is_inside(&self, pos: Vector) -> bool {
+        self.boundary.contains(&pos)
+    }
+
+    /// constant directional force, e.g. (0, 1) pulls particles down;
+    /// its length relative to 1 (Brownian motion) sets drift strength
+    pub fn set_gravity(&mut self, gravity: Vector) {
+        self.gravity = gravity;
+    }
+    /// center attractor multiplier, 0 disables the attractor
+    pub fn set_attractor_strength(&mut self, strength: f64) {
+        self.attractor_strength = strength;
+    }
+
+    /// original walk: unit speed, direction nudged every update
+    pub fn set_legacy_walk(&mut self, species: u8) {
+        self.set_walk(species, WalkModel::Legacy);
+    }
+    /// Brownian motion with Gaussian displacements and given diffusion coefficient
+    pub fn set_gaussian_walk(&mut self, species: u8, diffusion: f64) {
+        self.set_walk(species, WalkModel::Gaussian { diffusion });
+    }
+    /// run-and-tumble walk at unit speed with given turning rate
+    pub fn set_persistent_walk(&mut self, species: u8, turn_rate: f64) {
+        self.set_walk(species, WalkModel::Persistent { turn_rate });
+    }
+    /// Lévy flight with step length tail exponent alpha, truncated to [min_step, max_step]
+    pub fn set_levy_walk(&mut self, species: u8, alpha: f64, min_step: f64, max_step: f64) {
+        self.set_walk(
+            species,
+            WalkModel::Levy {
+                alpha,
+                min_step,
+                max_step,
+            },
+        );
+    }
+    fn set_walk(&mut self, species: u8, walk: WalkModel) {
+        if let Some(species) = self.species.get_mut(species as usize) {
+            species.walk = walk;
+        }
+    }
+    /// speed of new particles: log-normal around median speed with spread in log units;
+    /// speed drops e times every decay_time (0 disables decay)
+    pub fn set_mobility(&mut self, species: u8, speed: f64, spread: f64, decay_time: f64) {
+        if let Some(species) = self.species.get_mut(species as usize) {
+            species.mobility = Mobility {
+                speed,
+                spread,
+                decay_time,
+            };
+        }
+    }
+    /// random rotation noise of moving particles, degrees squared per time unit;
+    /// affects growth only with port or free attachment alignment
+    pub fn set_rotational_diffusion(&mut self, species: u8, diffusion: f64) {
+        if let Some(species) = self.species.get_mut(species as usize) {
+            species.rotational_diffusion = diffusion;
+        }
+    }
+    /// rotate moving particles towards direction (degrees) with at most given
+    /// angular velocity; particles with n-fold symmetry (order) align every 360/n degrees.
+    /// Attaching particles with free or port alignment additionally turn by binding_bias
+    /// of their misalignment, and bonds across the field are rejected with growth_bias probability
+    pub fn set_aligning_field(
+        &mut self,
+        direction: f64,
+        torque: f64,
+        order: u8,
+        binding_bias: f64,
+        growth_bias: f64,
+    ) {
+        self.aligning_field = Some(AligningField::new(
+            direction,
+            torque,
+            order,
+            binding_bias,
+            growth_bias,
+        ));
+    }
+    pub fn clear_aligning_field(&mut self) {
+        self.aligning_field = None;
+    }
+    /// how attaching particles are turned: zero (crystal, default) ignores their own
+    /// rotation, port and free keep it, so rotational diffusion and the aligning field matter
+    pub fn set_attachment_alignment(&mut self, align: AttachmentAlignment) {
+        self.bind_cfgs[0].set_alignment(align);
+    }
+    /// current engine time (same clock as particle `since` timestamps)
+    pub fn set_time(&mut self, now: f64) {
+        self.time = now;
+    }
+
+    /// remove or respawn moving particles older than ttl
+    pub fn set_lifetime(&mut self, ttl: f64, action: ExpiryAction) {
+        self.ttl = Some(ttl);
+        self.expiry_action = action;
+    }
+    pub fn clear_lifetime(&mut self) {
+        self.ttl = None;
+    }
+    pub fn wander_statistics(&self) -> WanderStatistics {
+        self.wander_stats
+    }
+    pub fn reset_wander_statistics(&mut self) {
+        self.wander_stats = WanderStatistics::default();
+    }
+
+    /// growth metrics ring buffer, see `GrowthSample` for layout;
+    /// samples go from slot `telemetry_head()` onwards, wrapping at capacity
+    pub fn telemetry_ptr(&self) -> *const f64 {
+        self.telemetry.as_ptr()
+    }
+    /// the whole ring buffer as an f64 array over wasm memory
+    /// (`telemetry_capacity()` samples); like particle views, it is detached
+    /// when wasm memory grows
+    pub fn telemetry_view(&self) -> Float64Array {
+        unsafe { Float64Array::view(self.telemetry.as_slice()) }
+    }
+    pub fn telemetry_len(&self) -> usize {
+        self.telemetry.len()
+    }
+    pub fn telemetry_head(&self) -> usize {
+        self.telemetry.head()
+    }
+    pub fn telemetry_capacity(&self) -> usize {
+        self.telemetry.capacity()
+    }
+    /// number of f64 values per sample
+    pub fn telemetry_stride(&self) -> usize {
+        SAMPLE_STRIDE
+    }
+    pub fn clear_telemetry(&mut self) {
+        self.telemetry.clear();
+    }
+
+    /// push moving particles apart, from strength at contact down to zero at range
+    pub fn set_soft_repulsion(&mut self, range: f64, strength: f64) {
+        self.repulsion = Repulsion::Soft { range, strength };
+    }
+    /// keep moving particle centres at least radius apart
+    pub fn set_hard_core_exclusion(&mut self, radius: f64) {
+        self.repulsion = Repulsion::HardCore { radius };
+    }
+    /// let moving particles pass through each other (default)
+    pub fn clear_repulsion(&mut self) {
+        self.repulsion = Repulsion::None;
+    }
+
+    /// cluster-cluster aggregation: moving particles bond to each other into rigid
+    /// clusters, which drift as a whole and freeze when any member attaches
+    pub fn set_cluster_aggregation(&mut self, enabled: bool) {
+        self.aggregation = enabled;
+    }
+    /// clusters of N particles move N^-exponent times as fast as free particles
+    /// (0 for size-independent mobility)
+    pub fn set_cluster_mobility_exponent(&mut self, exponent: f64) {
+        if exponent.is_finite() {
+            self.cluster_mobility_exponent = exponent;
+        }
+    }
+    /// number of drifting clusters of two or more moving particles
+    pub fn moving_clusters_count(&self) -> usize {
+        self.mp_container.group_by_cluster_id().len()
+    }
+
+    /// add a wall segment obstacle
+    pub fn add_segment_obstacle(&mut self, from: Vector, to: Vector) {
+        self.obstacles.add_obstacle(Obstacle::Segment(from, to));
+    }
+    /// add a solid circle obstacle
+    pub fn add_circle_obstacle(&mut self, center: Vector, radius: f64) {
+        self.obstacles
+            .add_obstacle(Obstacle::Circle { center, radius });
+    }
+    /// add a solid polygon obstacle, vertices as flat [x0, y0, x1, y1, ...] list
+    pub fn add_polygon_obstacle(&mut self, vertices: &[f64]) {
+        if vertices.len() >= 6 {
+            self.obstacles.add_obstacle(Obstacle::Polygon(
+                vertices
+                    .chunks_exact(2)
+                    .map(|xy| Vector::new(xy[0], xy[1]))
+                    .collect(),
+            ));
+        }
+    }
+    pub fn clear_obstacles(&mut self) {
+        self.obstacles.clear();
+    }
+    pub fn obstacles_count(&self) -> usize {
+        self.obstacles.size()
+    }
+
+    /// forbid regions where greyscale bitmap pixels (row-major, one byte per pixel,
+    /// stretched over the field) are at or above threshold
+    pub fn set_forbidden_mask(&mut self, data: &[u8], width: usize, height: usize, threshold: u8) {
+        self.forbidden_mask =
+            Mask::new(data, width, height, &self.dimensions).map(|mask| mask.threshold(threshold));
+    }
+    /// make spawn density proportional to greyscale bitmap brightness
+    pub fn set_spawn_mask(&mut self, data: &[u8], width: usize, height: usize) {
+        self.spawn_mask = Mask::new(data, width, height, &self.dimensions);
+    }
+    pub fn clear_masks(&mut self) {
+        self.forbidden_mask = None;
+        self.spawn_mask = None;
+    }
+
+    /// check if a position is inside an obstacle or a forbidden region
+    fn is_obstructed(&self, pos: &Vector) -> bool {
+        self.forbidden_mask.iter().any(|mask| mask.is_set(pos))
+            || self
+                .obstacles
+                .select_for_collision(pos, 0.)
+                .into_iter()
+                .any(|obstacle| obstacle.contains(pos))
+    }
+
+    /// check if a straight path crosses an obstacle
+    fn is_path_obstructed(&self, from: &Vector, to: &Vector) -> bool {
+        let center = (*from + *to) * 0.5;
+        let range = Vector::length(&Vector::diff(to, from)) * 0.5;
+        self.obstacles
+            .select_for_collision(&center, range)
+            .into_iter()
+            .any(|obstacle| obstacle.blocks(from, to))
+            || self.forbidden_mask.iter().any(|mask| mask.is_set(to))
+    }
+
+    /// check if a bond formed by binding would cross an obstacle
+    fn is_binding_obstructed(
+        &self,
+        moving: &MovingParticle,
+        fixed: &StaticParticle,
+        binding: BindingResult,
+    ) -> bool {
+        (self.obstacles.size() > 0 || self.forbidden_mask.is_some())
+            && binding
+                .apply_binding(
+                    moving,
+                    &mut fixed.clone(),
+                    &self.bind_cfgs[0],
+                    &self.bind_cfgs[0],
+                )
+                .map(|bound| self.is_path_obstructed(&fixed.pos, &bound.pos))
+                .unwrap_or(true)
+    }
+
+    /// random spawn position outside of obstacles, following spawn mask density
+    fn random_spawn_pos<F>(&self, sample: F) -> Option<Vector>
+    where
+        F: Fn(&Boundary) -> Option<Vector>,
+    {
+        (0..SPAWN_ATTEMPTS)
+            .filter_map(|_| sample(&self.boundary))
+            .find(|pos| {
+                !self.is_obstructed(pos)
+                    && self
+                        .spawn_mask
+                        .iter()
+                        .all(|mask| extfn::random() < mask.weight(pos))
+            })
+    }
+
+    /// soft repulsion force on every moving particle from its neighbours
+    /// (empty unless soft repulsion is enabled)
+    fn repulsion_forces(&self) -> Vec<Vector> {
+        if let Repulsion::Soft { range, .. } = self.repulsion {
+            self.mp_container
+                .values()
+                .map(|moving| {
+                    self.mp_container
+                        .select_nearby(&moving.particle.pos, range)
+                        .into_iter()
+                        .filter(|other| other.index != moving.index)
+                        .fold(Vector::new(0., 0.), |force, other| {
+                            let image = self.image_near(other.particle, &moving.particle.pos);
+                            force
+                                + self
+                                    .repulsion
+                                    .force(&Vector::diff(&moving.particle.pos, &image.pos))
+                        })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+    /// indices of moving particles that every moving particle may run into during a step
+    /// (empty unless hard core exclusion is enabled)
+    fn hard_core_neighbours(&self, delta: f64) -> Vec<Vec<usize>> {
+        if let Repulsion::HardCore { radius } = self.repulsion {
+            let step =
+                |moving: &MovingParticle| Vector::length(&moving.vel) * moving.speed * delta.abs();
+            let max_step = self
+                .mp_container
+                .values()
+                .map(|moving| step(moving.particle))
+                .fold(0., f64::max);
+            self.mp_container
+                .values()
+                .map(|moving| {
+                    let reach = radius + step(moving.particle) + max_step;
+                    self.mp_container
+                        .select_nearby(&moving.particle.pos, reach)
+                        .into_iter()
+                        .filter(|other| other.index != moving.index)
+                        .map(|other| other.index)
+                        .collect()
+                })
+                .collect()
+        } else {
+            Vec::new()
         }
     }
 
+    /// field period (full width and height) for periodic boundary
+    fn period(&self) -> Option<Vector> {
+        match self.boundary_behaviour() {
+            BoundaryBehaviour::Periodic => Some(self.boundary.half_extents() * 2.),
+            _ => None,
+        }
+    }
+    /// propagate field period to neighbour queries
+    fn sync_period(&mut self) {
+        let period = self.period();
+        self.mp_container.set_period(period);
+        self.sp_container.set_period(period);
+    }
+    /// copy of a moving particle, turned towards the aligning field before binding
+    fn aligned_for_binding(&self, moving: MovingParticle) -> MovingParticle {
+        match self.aligning_field {
+            Some(field) => MovingParticle {
+                rot: field.bias_rotation(moving.rot),
+                ..moving
+            },
+            None => moving,
+        }
+    }
+    /// check if a new bond passes the aligning field growth bias (random)
+    fn accepts_growth(&self, fixed: &StaticParticle, binding: &BindingResult) -> bool {
+        let direction = binding.port_direction(fixed, &self.bind_cfgs[0]);
+        self.aligning_field
+            .iter()
+            .all(|field| extfn::random() < field.growth_probability(direction))
+    }
+    /// copy of a moving particle, moved to its image closest to an anchor point
+    /// (minimum image convention), so that binding works across periodic field edges
+    fn image_near(&self, moving: &MovingParticle, anchor: &Vector) -> MovingParticle {
+        match self.period() {
+            Some(period) => MovingParticle {
+                pos: *anchor + Vector::min_image_diff(&moving.pos, anchor, &period),
+                ..*moving
+            },
+            None => *moving,
+        }
+    }
+
+    /// reseed random generator used for seed patterns
+    pub fn set_random_seed(&mut self, seed: u32) {
+        self.rng = Rng::new(seed as u64);
+    }
+
+    /// replicate every new static particle under n-fold rotations around
+    /// the center, optionally with reflections
+    pub fn set_symmetry(&mut self, order: u8, reflect: bool, center: Vector) {
+        self.symmetry = Some(Symmetry::new(order, reflect, center));
+    }
+    /// disable symmetric growth
+    pub fn clear_symmetry(&mut self) {
+        self.symmetry = None;
+    }
+
     pub fn moving_particles_ptr(&self) -> *const MovingParticle {
         self.mp_container.as_ptr()
     }
     pub fn static_particles_ptr(&self) -> *const StaticParticle {
         self.sp_container.as_ptr()
     }
+    pub fn bond_segments_ptr(&self) -> *const BondSegment {
+        self.bonds.as_ptr()
+    }
+    /// Buffers as f64 arrays over wasm memory, laid out as described by
+    /// `MovingParticle::layout`, `StaticParticle::layout` and `BondSegment::layout`.
+    /// A view is detached when wasm memory grows, so take fresh views every frame
+    /// and do not call into the field while reading them
+    pub fn moving_particles_view(&self) -> Float64Array {
+        unsafe { Float64Array::view(layout::as_slots(self.mp_container.as_slice())) }
+    }
+    pub fn static_particles_view(&self) -> Float64Array {
+        unsafe { Float64Array::view(layout::as_slots(self.sp_container.as_slice())) }
+    }
+    pub fn bond_segments_view(&self) -> Float64Array {
+        unsafe { Float64Array::view(layout::as_slots(&self.bonds)) }
+    }
     pub fn moving_particles_count(&self) -> usize {
         self.mp_container.size()
     }
     pub fn static_particles_count(&self) -> usize {
         self.sp_container.size()
     }
+    pub fn bond_segments_count(&self) -> usize {
+        self.bonds.len()
+    }
+    /// static particle positions, for analysis
+    fn static_positions(&self) -> Vec<Vector> {
+        self.sp_container
+            .values()
+            .map(|fixed| fixed.particle.pos)
+            .collect()
+    }
+    /// fractal dimensions and radius of gyration of static particles
+    pub fn fractal_analysis(&self) -> FractalAnalysis {
+        let points = self.static_positions();
+        FractalAnalysis::new(&points, self.bind_cfgs[0].radius())
+    }
+    /// busy port, branch length and Horton-Strahler order statistics of static particles
+    pub fn branch_statistics(&self) -> BranchStatistics {
+        let busy_ports = self
+            .sp_container
+            .values()
+            .map(|fixed| fixed.particle.busy_ports())
+            .collect::<Vec<_>>();
+        BranchStatistics::new(&busy_ports, &self.bond_tree)
+    }
+    /// angular and radial density of static particles around a centre,
+    /// with angular harmonics to measure rotational symmetry
+    pub fn density_profile(
+        &self,
+        center: Vector,
+        angular_bins: usize,
+        radial_bins: usize,
+        ring_width: f64,
+    ) -> DensityProfile {
+        let points = self.static_positions();
+        DensityProfile::new(&points, &center, angular_bins, radial_bins, ring_width)
+    }
+    /// pairs of static particles within binding radius of each other
+    fn proximity_edges(&self) -> Vec<(usize, usize)> {
+        let bind_cfg = &self.bind_cfgs[0];
+        let period = self.period();
+        let mut edges = Vec::new();
+        for fixed in self.sp_container.values() {
+            let pos = fixed.particle.pos;
+            for other in self.sp_container.select_nearby(&pos, bind_cfg.radius()) {
+                let other_pos = match period {
+                    Some(period) => {
+                        pos + Vector::min_image_diff(&other.particle.pos, &pos, &period)
+                    }
+                    None => other.particle.pos,
+                };
+                if other.index > fixed.index && bind_cfg.close_enough_to_bind(&pos, &other_pos) {
+                    edges.push((fixed.index, other.index));
+                }
+            }
+        }
+        edges
+    }
+    /// separate crystals of static particles, with their sizes, centroids and bounding boxes
+    pub fn components(&self, connectivity: Connectivity) -> Components {
+        let points = self.static_positions();
+        let edges = match connectivity {
+            Connectivity::Bonds => (0..points.len())
+                .filter_map(|index| self.bond_tree.parent(index).map(|parent| (index, parent)))
+                .collect::<Vec<_>>(),
+            Connectivity::Proximity => self.proximity_edges(),
+        };
+        Components::new(&points, edges)
+    }
+    /// static particle positions of every crystal, in order of component ids
+    fn crystal_positions(&self, connectivity: Connectivity) -> Vec<Vec<Vector>> {
+        let labels = self.components(connectivity).labels();
+        let mut crystals = Vec::<Vec<Vector>>::new();
+        for (fixed, &label) in self.sp_container.values().zip(labels.iter()) {
+            let label = label as usize;
+            if crystals.len() <= label {
+                crystals.resize(label + 1, Vec::new());
+            }
+            crystals[label].push(fixed.particle.pos);
+        }
+        crystals
+    }
+    /// convex outline of every crystal, as a flat list: vertex count,
+    /// then x and y of every vertex, for every crystal in turn
+    pub fn convex_hull_outlines(&self, connectivity: Connectivity) -> Vec<f64> {
+        let outlines = self
+            .crystal_positions(connectivity)
+            .iter()
+            .map(|points| hull::convex_hull(points))
+            .collect::<Vec<_>>();
+        hull::flatten_outlines(&outlines)
+    }
+    /// concave outline (alpha shape) of every crystal, same layout as convex hulls;
+    /// alpha is the probe radius, about binding radius follows crystal branches
+    pub fn alpha_shape_outlines(&self, connectivity: Connectivity, alpha: f64) -> Vec<f64> {
+        let outlines = self
+            .crystal_positions(connectivity)
+            .iter()
+            .map(|points| hull::alpha_shape(points, alpha))
+            .collect::<Vec<_>>();
+        hull::flatten_outlines(&outlines)
+    }
+    /// SVG document with outlines (as returned by outline functions) over the field
+    pub fn outlines_svg(&self, outlines: &[f64]) -> String {
+        hull::outlines_to_svg(outlines, &self.dimensions)
+    }
+    /// Delaunay triangulation of static particles, their Voronoi cells clipped
+    /// to the outer field boundary, and nearest neighbour statistics;
+    /// cells stay convex, so in an annulus field they extend across the hole
+    pub fn tessellation(&self) -> Tessellation {
+        let points = self.static_positions();
+        Tessellation::new(&points, &self.boundary.outline(BOUNDARY_OUTLINE_SEGMENTS))
+    }
+
+    /// set or clear a state flag of a moving particle; indices follow the moving
+    /// particle buffer and change when other particles are removed
+    pub fn set_particle_flag(&mut self, index: usize, flag: ParticleFlag, on: bool) -> bool {
+        match self.mp_container.at(index).copied() {
+            Some(mut particle) => {
+                particle.set_flag(flag, on);
+                self.mp_container.update(&Particle { particle, index });
+                true
+            }
+            None => false,
+        }
+    }
+    /// indices of moving particles with a state flag set
+    pub fn particles_with_flag(&self, flag: ParticleFlag) -> Vec<u32> {
+        self.mp_container
+            .values()
+            .filter(|moving| moving.particle.has_flag(flag))
+            .map(|moving| moving.index as u32)
+            .collect()
+    }
 
     /// add a particle anywhere in the field
     pub fn add_particle(&mut self) {
         if !self.mp_container.is_full() {
-            let pos = Field::random_pos_in_field(&self.dimensions);
-            let vel = Field::random_vel_in_field();
-
-            self.mp_container.add_particle(MovingParticle {
-                pos,
-                vel,
-                since: 0.,
-                flags: 0,
-            });
+            if let Some(pos) = self.random_spawn_pos(Boundary::random_pos) {
+                let vel = Field::random_vel_in_field();
+
+                self.mp_container.add_particle(MovingParticle {
+                    pos,
+                    vel,
+                    since: self.time,
+                    flags: 0,
+                    speed: self.species[0].mobility.sample_speed(),
+                    rot: Field::random_rot(),
+                    spin: 0.,
+                });
+            }
         }
     }
 
-    /// add a particle on the field boundary
+    /// add a particle of given species at a position, marked as seeded
+    pub fn add_particle_at(&mut self, pos: Vector, species: u8) -> bool {
+        if self.mp_container.is_full()
+            || (species as usize) >= MAX_SPECIES
+            || !self.boundary.contains(&pos)
+            || self.is_obstructed(&pos)
+        {
+            return false;
+        }
+        let mut particle = MovingParticle {
+            pos,
+            vel: Field::random_vel_in_field(),
+            since: self.time,
+            flags: 0,
+            speed: self.species[species as usize].mobility.sample_speed(),
+            rot: Field::random_rot(),
+            spin: 0.,
+        };
+        particle.set_species(species);
+        particle.set_flag(ParticleFlag::Seeded, true);
+        self.mp_container.add_particle(particle).is_some()
+    }
+
+    /// add a particle on the field boundary (or in another spawn region)
     pub fn add_boundary_particle(&mut self, since: f64) {
-        if !self.mp_container.is_full() {
-            let pos = Field::random_boundary_pos_in_field(&self.dimensions);
-            let vel = Field::random_vel_in_field();
+        self.add_boundary_particle_of_species(since, 0);
+    }
 
-            self.mp_container.add_particle(MovingParticle {
-                pos,
-                vel,
-                since,
-                flags: 0,
-            });
+    /// add a particle of given species on the field boundary (or in another spawn region)
+    pub fn add_boundary_particle_of_species(&mut self, since: f64, species: u8) {
+        if !self.mp_container.is_full() && (species as usize) < MAX_SPECIES {
+            let band = 2. * self.boundary.half_extents().y * TOP_SPAWN_BAND;
+            let spawned = match self.spawn_region {
+                SpawnRegion::Boundary => self.random_spawn_pos(|b| Some(b.random_boundary_pos())),
+                SpawnRegion::Top => self.random_spawn_pos(|b| b.random_top_pos(band)),
+            };
+            if let Some(pos) = spawned {
+                let vel = Field::random_vel_in_field();
+
+                let mut particle = MovingParticle {
+                    pos,
+                    vel,
+                    since,
+                    flags: 0,
+                    speed: self.species[species as usize].mobility.sample_speed(),
+                    rot: Field::random_rot(),
+                    spin: 0.,
+                };
+                particle.set_species(species);
+                self.mp_container.add_particle(particle);
+            }
         }
     }
 
-    /// try adding a static particle directly (with respect to binding sites)
+    /// try adding a static particle directly (with respect to binding sites
+    /// and field symmetry)
     pub fn add_static_particle(&mut self, pos: Vector) -> bool {
-        if self.mp_container.is_full() || self.sp_container.is_full() {
+        let index = self.sp_container.size();
+        self.place_static_particle(pos) && {
+            self.replicate_static_particle(index);
+            true
+        }
+    }
+
+    /// place a random constellation of seeds with n-fold rotational symmetry
+    /// around the field center; return number of static particles added
+    pub fn seed_symmetric_random(&mut self, points: u32, order: u8, radius: f64) -> usize {
+        let positions = seeds::symmetric_random(&mut self.rng, points, order, radius);
+        self.place_seeds(positions)
+    }
+    /// place a circle of seeds; return number of static particles added
+    pub fn seed_circle(&mut self, center: Vector, radius: f64, spacing: f64) -> usize {
+        if !self.is_seed_spacing(spacing) {
+            return 0;
+        }
+        let positions = seeds::circle(&center, radius, spacing, self.seed_capacity());
+        self.place_seeds(positions)
+    }
+    /// place a line segment of seeds; return number of static particles added
+    pub fn seed_line(&mut self, from: Vector, to: Vector, spacing: f64) -> usize {
+        if !self.is_seed_spacing(spacing) {
+            return 0;
+        }
+        let positions = seeds::line(&from, &to, spacing, self.seed_capacity());
+        self.place_seeds(positions)
+    }
+    /// place a regular polygon outline of seeds (rotation in degrees);
+    /// return number of static particles added
+    pub fn seed_polygon(
+        &mut self,
+        center: Vector,
+        radius: f64,
+        sides: u32,
+        rotation: f64,
+        spacing: f64,
+    ) -> usize {
+        if !self.is_seed_spacing(spacing) {
+            return 0;
+        }
+        let limit = self.seed_capacity();
+        let positions = seeds::polygon(&center, radius, sides, rotation, spacing, limit);
+        self.place_seeds(positions)
+    }
+    /// place an Archimedean spiral of seeds; return number of static particles added
+    pub fn seed_spiral(&mut self, center: Vector, turns: f64, pitch: f64, spacing: f64) -> usize {
+        if !self.is_seed_spacing(spacing) {
+            return 0;
+        }
+        let positions = seeds::spiral(&center, turns, pitch, spacing, self.seed_capacity());
+        self.place_seeds(positions)
+    }
+    /// place a horizontal substrate line at the bottom of the field;
+    /// return number of static particles added
+    pub fn seed_substrate(&mut self, height: f64, spacing: f64) -> usize {
+        if !self.is_seed_spacing(spacing) {
+            return 0;
+        }
+        let positions = seeds::substrate(&self.boundary, height, spacing, self.seed_capacity());
+        self.place_seeds(positions)
+    }
+    /// place seeds on a grid wherever greyscale bitmap pixels (row-major, one byte per pixel,
+    /// stretched over the field) are at or above threshold;
+    /// return number of static particles added
+    pub fn seed_from_mask(
+        &mut self,
+        data: &[u8],
+        width: usize,
+        height: usize,
+        threshold: u8,
+        spacing: f64,
+    ) -> usize {
+        if !self.is_seed_spacing(spacing) {
+            return 0;
+        }
+        let positions = Mask::new(data, width, height, &self.dimensions)
+            .map(|mask| mask.threshold(threshold).grid_positions(spacing))
+            .unwrap_or_default();
+        self.place_seeds(positions)
+    }
+
+    /// seed patterns with spacing below binding radius place nothing,
+    /// as such seeds could not all fit
+    fn is_seed_spacing(&self, spacing: f64) -> bool {
+        spacing >= self.bind_cfgs[0].radius()
+    }
+    /// number of static particles that can still be added
+    fn seed_capacity(&self) -> usize {
+        self.sp_container.max_size() - self.sp_container.size()
+    }
+
+    /// add static particles one by one, return how many were actually added
+    fn place_seeds(&mut self, positions: Vec<Vector>) -> usize {
+        let initial_count = self.sp_container.size();
+        for pos in positions {
+            self.add_static_particle(pos);
+        }
+        self.sp_container.size() - initial_count
+    }
+
+    /// try adding a static particle directly, without symmetric replication
+    fn place_static_particle(&mut self, pos: Vector) -> bool {
+        if self.mp_container.is_full()
+            || self.sp_container.is_full()
+            || !self.boundary.contains(&pos)
+        {
             return false;
         }
         // to align added particles, we need to treat them as moving first and then convert them to static
@@ -163,54 +984,348 @@ impl Field {
             vel,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: Field::random_rot(),
+            spin: 0.,
         };
 
         match self.check_single_particle_attachment(&new_particle) {
             AttachmentCheckResult::Ok(mut static_particle, binding) => {
                 // apply binding
-                self.convert_mp_to_static(&new_particle, &mut static_particle, binding)
+                let moving = self.image_near(&new_particle, &static_particle.particle.pos);
+                self.bind_mp_to_static(&moving, &mut static_particle, binding)
             }
             AttachmentCheckResult::NoOtherParticle => {
                 // no other static particles found in vicinity, just create a new one
-                self.sp_container
-                    .add_particle(StaticParticle {
-                        pos,
-                        rot: 0.,
-                        binding_cfg_id: 0,
-                    })
-                    .is_some()
+                !self.is_obstructed(&pos)
+                    && self.add_bound_static_particle(
+                        StaticParticle {
+                            pos,
+                            rot: 0.,
+                            binding_cfg_id: 0,
+                        },
+                        None,
+                    )
             }
             AttachmentCheckResult::SitesBusy => false,
         }
     }
 
-    /// make a moving particle static by attaching to another static particle
+    /// make a moving particle static by attaching to another static particle,
+    /// together with the rest of its drifting cluster (indices of other members),
+    /// then replicate it according to field symmetry;
+    /// nothing is bound unless the whole cluster fits
     fn convert_mp_to_static(
         &mut self,
         moving_particle: &MovingParticle,
         static_particle: &mut Particle<StaticParticle>,
         binding_result: BindingResult,
+        cluster: &[usize],
+    ) -> bool {
+        if !cluster.is_empty()
+            && !self.cluster_fits_binding(
+                moving_particle,
+                &static_particle.particle,
+                binding_result,
+                cluster,
+            )
+        {
+            return false;
+        }
+        let bound_index = self.sp_container.size();
+        self.bind_mp_to_static(moving_particle, static_particle, binding_result) && {
+            if let Some(bound) = self.sp_container.at(bound_index).copied() {
+                let offset = Vector::diff(&bound.pos, &moving_particle.pos);
+                self.freeze_cluster_members(cluster, bound_index, offset);
+            }
+            self.replicate_static_particle(bound_index);
+            true
+        }
+    }
+
+    /// replicate a static particle under field symmetry;
+    /// every image goes through the usual attachment checks and may be rejected
+    fn replicate_static_particle(&mut self, index: usize) {
+        let pos = self.sp_container.at(index).map(|particle| particle.pos);
+        if let (Some(symmetry), Some(pos)) = (self.symmetry, pos) {
+            for image in symmetry.images(&pos) {
+                self.place_static_particle(image);
+            }
+        }
+    }
+
+    /// make a moving particle static by attaching to another static particle
+    fn bind_mp_to_static(
+        &mut self,
+        moving_particle: &MovingParticle,
+        static_particle: &mut Particle<StaticParticle>,
+        binding_result: BindingResult,
     ) -> bool {
         !self.sp_container.is_full() &&
             // apply binding operation to convert moving particle to static
-            if let Some(new_static_particle) = binding_result.apply_binding(
+            if let Some(mut new_static_particle) = binding_result.apply_binding(
                 moving_particle,
                 &mut static_particle.particle,
                 &self.bind_cfgs[0],
                 &self.bind_cfgs[0],
             ) {
+                // bound particle may stick out across a periodic field edge
+                if self.period().is_some() {
+                    new_static_particle.pos = self.boundary.wrap(&new_static_particle.pos);
+                }
+                // ports bonded inside a drifting cluster stay busy
+                new_static_particle.binding_cfg_id |= (moving_particle.busy_ports() as u64) << 32;
                 // update bound static particle, because it is a copy of the real thing
                 self.sp_container.update(&static_particle);
                 // move to static list
-                self.sp_container.add_particle(new_static_particle).is_some()
+                self.add_bound_static_particle(new_static_particle, Some(static_particle.index))
             }
             else { false }
     }
 
+    /// add a static particle and remember which one it is bound to
+    fn add_bound_static_particle(
+        &mut self,
+        particle: StaticParticle,
+        parent: Option<usize>,
+    ) -> bool {
+        let index = self.sp_container.size();
+        self.sp_container.add_particle(particle).is_some() && {
+            self.bond_tree.record(index, parent);
+            if let Some(from) = parent.and_then(|parent| self.sp_container.at(parent)) {
+                let to = match self.period() {
+                    Some(period) => {
+                        from.pos + Vector::min_image_diff(&particle.pos, &from.pos, &period)
+                    }
+                    None => particle.pos,
+                };
+                self.bonds.push(BondSegment {
+                    from: from.pos,
+                    to,
+                    port: self.bind_cfgs[0]
+                        .port_towards(from, &to)
+                        .map_or(-1., |port| port as f64),
+                    generation: self.bond_tree.generation(index) as f64,
+                });
+            }
+            true
+        }
+    }
+
     /// update particle positions according to time delta
     pub fn update_positions(&mut self, delta: f64) {
+        let boundary = &self.boundary;
+        let behaviour = self.boundary_behaviour();
+        let obstacles = &self.obstacles;
+        let forbidden_mask = &self.forbidden_mask;
+        let species = &self.species;
+        let now = self.time;
+        let repulsion = self.repulsion;
+        let period = self.period();
+        let neighbours = self.hard_core_neighbours(delta);
+        let aggregation = self.aggregation;
+        // current positions of particles with hard core, updated as particles move
+        let positions = self
+            .mp_container
+            .values()
+            .take(neighbours.len())
+            .map(|moving| Cell::new(moving.particle.pos))
+            .collect::<Vec<_>>();
+        let separation = |to: &Vector, from: &Vector| match period {
+            Some(period) => Vector::length(&Vector::min_image_diff(to, from, &period)),
+            None => Vector::length(&Vector::diff(to, from)),
+        };
+
+        let move_particle = |index: usize, p: &mut MovingParticle| {
+            // drifting clusters are moved as a whole below
+            if p.has_flag(ParticleFlag::Frozen) || (aggregation && p.cluster() != 0) {
+                return;
+            }
+            let speed = species
+                .get(p.species())
+                .map_or(1., |s| s.mobility.decay(now - p.since))
+                * p.speed;
+            let old_pos = p.pos;
+            p.pos += p.vel * (speed * delta);
+            p.rot = (p.rot + p.spin * delta).rem_euclid(360.);
+
+            // bounce off obstacles
+            let step = Vector::length(&(p.vel * (speed * delta)));
+            if let Some(obstacle) = obstacles
+                .select_for_collision(&old_pos, step)
+                .into_iter()
+                .find(|obstacle| obstacle.blocks(&old_pos, &p.pos))
+            {
+                let normal = obstacle.normal(&old_pos);
+                if Vector::dot(&p.vel, &normal) < 0. {
+                    p.vel = Vector::reflect(&p.vel, &normal);
+                }
+                p.pos = if obstacle.contains(&old_pos) {
+                    // caught inside (e.g. obstacle added on top of it), step out to the surface
+                    obstacle.closest_surface_point(&old_pos) + normal * OBSTACLE_CLEARANCE
+                } else {
+                    old_pos
+                };
+                return;
+            }
+            // forbidden regions have no surface normal, so just turn back
+            // (particles already inside, e.g. after mask change, are let out)
+            if forbidden_mask
+                .iter()
+                .any(|mask| mask.is_set(&p.pos) && !mask.is_set(&old_pos))
+            {
+                p.vel = p.vel * -1.;
+                p.pos = old_pos;
+                return;
+            }
+            // keep clear of other moving particles
+            if neighbours.get(index).into_iter().flatten().any(|&other| {
+                let other_pos = positions[other].get();
+                repulsion.blocks(
+                    separation(&old_pos, &other_pos),
+                    separation(&p.pos, &other_pos),
+                )
+            }) {
+                p.pos = old_pos;
+                return;
+            }
+
+            if boundary.contains(&p.pos) {
+                return;
+            }
+            match behaviour {
+                BoundaryBehaviour::Reflect => {
+                    let normal = boundary.normal(&p.pos);
+                    if Vector::dot(&p.vel, &normal) > 0. {
+                        p.vel = Vector::reflect(&p.vel, &normal);
+                    }
+                    // particles already outside (e.g. after boundary change) keep moving inwards
+                    if boundary.contains(&old_pos) {
+                        p.pos = old_pos;
+                    }
+                }
+                BoundaryBehaviour::Periodic => p.pos = boundary.wrap(&p.pos),
+                // absorbed particles are removed below
+                BoundaryBehaviour::Absorb => (),
+            }
+        };
+        self.mp_container.apply(|index, p| {
+            move_particle(index, p);
+            // particles moving later see where this one ended up
+            if let Some(position) = positions.get(index) {
+                position.set(p.pos);
+            }
+        });
+        if self.aggregation {
+            self.move_clusters(delta);
+        }
+
+        if behaviour == BoundaryBehaviour::Absorb {
+            let absorbed = self.select_with_clusters(|moving| !self.boundary.contains(&moving.pos));
+            self.mp_container.remove_multiple_by_index(absorbed);
+        }
+    }
+
+    /// move every drifting cluster by one step shared by all members, or turn it
+    /// back if any member is stopped (by obstacles, walls or other particles);
+    /// a cluster with a frozen member does not move
+    fn move_clusters(&mut self, delta: f64) {
+        let now = self.time;
+        let period = self.period();
+        let separation = |to: &Vector, from: &Vector| match period {
+            Some(period) => Vector::length(&Vector::min_image_diff(to, from, &period)),
+            None => Vector::length(&Vector::diff(to, from)),
+        };
+        let hard_core = match self.repulsion {
+            Repulsion::HardCore { radius } => radius,
+            _ => 0.,
+        };
+        for members in self.mp_container.group_by_cluster_id().values() {
+            let cluster = members
+                .iter()
+                .filter_map(|&index| self.mp_container.at(index).map(|&p| (index, p)))
+                .collect::<Vec<_>>();
+            let frozen = cluster
+                .iter()
+                .any(|(_, p)| p.has_flag(ParticleFlag::Frozen));
+            if cluster.is_empty() || frozen {
+                continue;
+            }
+            let size = cluster.len() as f64;
+            let speed = cluster
+                .iter()
+                .map(|(_, p)| {
+                    self.species
+                        .get(p.species())
+                        .map_or(1., |s| s.mobility.decay(now - p.since))
+                        * p.speed
+                })
+                .sum::<f64>()
+                / size;
+            // members share velocity, see `share_cluster_velocities`
+            let step =
+                cluster[0].1.vel * (speed * delta * size.powf(-self.cluster_mobility_exponent));
+            let reach = hard_core + Vector::length(&step);
+
+            let blocked = cluster.iter().any(|(_, p)| {
+                let to = p.pos + step;
+                self.is_path_obstructed(&p.pos, &to)
+                    || self
+                        .forbidden_mask
+                        .iter()
+                        .any(|mask| mask.is_set(&to) && !mask.is_set(&p.pos))
+                    || (self.boundary_behaviour() == BoundaryBehaviour::Reflect
+                        && !self.boundary.contains(&to)
+                        && self.boundary.contains(&p.pos))
+                    || (hard_core > 0.
+                        && self
+                            .mp_container
+                            .select_nearby(&p.pos, reach)
+                            .into_iter()
+                            .filter(|other| !members.contains(&other.index))
+                            .any(|other| {
+                                self.repulsion.blocks(
+                                    separation(&p.pos, &other.particle.pos),
+                                    separation(&to, &other.particle.pos),
+                                )
+                            }))
+            });
+            for (index, mut particle) in cluster {
+                if blocked {
+                    particle.vel = particle.vel * -1.;
+                } else {
+                    particle.pos += step;
+                    if self.boundary_behaviour() == BoundaryBehaviour::Periodic
+                        && !self.boundary.contains(&particle.pos)
+                    {
+                        particle.pos = self.boundary.wrap(&particle.pos);
+                    }
+                }
+                self.mp_container.relocate(&Particle { particle, index });
+            }
+        }
+    }
+
+    /// indices of moving particles matching a condition, along with all members
+    /// of their clusters (bonds inside a cluster cannot be undone)
+    fn select_with_clusters<F>(&self, condition: F) -> Vec<usize>
+    where
+        F: Fn(&MovingParticle) -> bool,
+    {
+        let clusters = self
+            .mp_container
+            .values()
+            .filter(|moving| condition(moving.particle))
+            .map(|moving| moving.particle.cluster())
+            .filter(|&cluster| cluster != 0)
+            .collect::<Vec<_>>();
         self.mp_container
-            .apply(|p: &mut MovingParticle| p.pos += p.vel * delta);
+            .values()
+            .filter(|moving| {
+                condition(moving.particle) || clusters.contains(&moving.particle.cluster())
+            })
+            .map(|moving| moving.index)
+            .collect()
     }
 
     /// simple center attractor vector, diminishes at the center
@@ -225,24 +1340,78 @@ impl Field {
     pub fn update_velocities(&mut self, delta: f64) {
         let field_dimenstions = self.dimensions;
         let num_static_particles = self.sp_container.size();
+        let gravity = self.gravity;
+        let attractor_strength = self.attractor_strength;
+        let species = &self.species;
+        let aligning_field = self.aligning_field;
+        let repulsion_forces = self.repulsion_forces();
 
-        self.mp_container.apply(|particle| {
-            // particle can always change its direction unpredictably (Brownian motion)
-            let new_dir = Field::random_vel_in_field();
+        self.mp_container.apply(|index, particle| {
+            if particle.has_flag(ParticleFlag::Frozen) {
+                return;
+            }
+            let particle_species = species
+                .get(particle.species())
+                .copied()
+                .unwrap_or_else(Species::new);
 
             // define an attractor at the center, so that every particle is eventually caught
             let attractor_vector = Self::center_attractor_vector(&particle.pos, &field_dimenstions);
             // accelerate when approaching attractor
-            let attractor_force = VELOCITY_FIELD_ATTENUATION
+            let attractor_force = attractor_strength
                 * (0.2 / (0.2 + Vector::length(&attractor_vector).max(1.))
                     + 0.5 * num_static_particles as f64 / MAX_STATIC as f64);
             // Importantly, field force affects particle density, which determines growth features
+            let drift = attractor_vector * attractor_force
+                + gravity
+                + repulsion_forces
+                    .get(index)
+                    .copied()
+                    .unwrap_or_else(|| Vector::new(0., 0.));
+
+            // particle can always change its direction unpredictably (Brownian motion)
+            particle.vel = particle_species.walk.velocity(&particle.vel, &drift, delta);
+
+            // same for rotation, with optional torque from an aligning field
+            let torque = aligning_field.map_or(0., |field| field.spin(particle.rot));
+            let rotational_noise = if delta > 0. {
+                walk::gaussian_pair().x
+                    * (2. * particle_species.rotational_diffusion / delta).sqrt()
+            } else {
+                0.
+            };
+            particle.spin = torque + rotational_noise;
+        });
+        if self.aggregation {
+            self.share_cluster_velocities();
+        }
+    }
 
-            particle.vel = Vector::normalize(
-                particle.vel
-                    // velocity changes according to delta, but is always normalized afterwards
-                    + Vector::normalize(attractor_vector * attractor_force + new_dir) * delta,
-            );
+    /// drifting clusters head in the mean direction of their members at their
+    /// mean speed; cluster size only enters through the mobility exponent
+    fn share_cluster_velocities(&mut self) {
+        let mut shared = vec![None; self.mp_container.size()];
+        for members in self.mp_container.group_by_cluster_id().values() {
+            let (total, speed) = members
+                .iter()
+                .filter_map(|&index| self.mp_container.at(index))
+                .fold((Vector::new(0., 0.), 0.), |(sum, speed), member| {
+                    (sum + member.vel, speed + Vector::length(&member.vel))
+                });
+            let velocity = if Vector::length(&total) > 0. {
+                Vector::normalize(total) * (speed / members.len() as f64)
+            } else {
+                total
+            };
+            for &index in members {
+                shared[index] = Some(velocity);
+            }
+        }
+        self.mp_container.apply(|index, particle| {
+            if let Some(vel) = shared[index] {
+                particle.vel = vel;
+                particle.spin = 0.;
+            }
         });
     }
 
@@ -267,10 +1436,21 @@ impl Field {
                 &moving.particle.pos,
                 bind_cfg.radius()
             ) {
+                let image =
+                    self.aligned_for_binding(self.image_near(moving.particle, &fixed.particle.pos));
                 if let Some(binding) =
-                    BindingResult::get_binding(moving.particle, fixed.particle, bind_cfg, bind_cfg)
+                    BindingResult::get_binding(&image, fixed.particle, bind_cfg, bind_cfg).filter(
+                        |&binding| {
+                            !self.is_binding_obstructed(&image, fixed.particle, binding)
+                                && self.accepts_growth(fixed.particle, &binding)
+                        },
+                    )
                 {
-                    results.push((moving.as_copy(), fixed.as_copy(), binding));
+                    let moving_image = Particle {
+                        particle: image,
+                        index: moving.index,
+                    };
+                    results.push((moving_image, fixed.as_copy(), binding));
                     if results.len() == 4 {
                         break 'outer;
                     }
@@ -286,48 +1466,356 @@ impl Field {
     fn check_single_particle_attachment(&self, moving: &MovingParticle) -> AttachmentCheckResult {
         let bind_cfg = &self.bind_cfgs[0];
 
-        let closest_static_particles: Vec<_> = self
-            .sp_container
-            .select_for_binding(&moving.pos, bind_cfg.radius())
+        let closest_static_particles: Vec<_> = self
+            .sp_container
+            .select_for_binding(&moving.pos, bind_cfg.radius())
+            .into_iter()
+            .filter(|fixed| {
+                let image = self.image_near(moving, &fixed.particle.pos);
+                bind_cfg.close_enough_to_bind(&fixed.particle.pos, &image.pos)
+            })
+            .collect();
+        if closest_static_particles.is_empty() {
+            return AttachmentCheckResult::NoOtherParticle;
+        }
+
+        closest_static_particles
+            .iter()
+            .find_map(|fixed_ref| {
+                let image = self.image_near(moving, &fixed_ref.particle.pos);
+                BindingResult::get_binding(&image, fixed_ref.particle, bind_cfg, bind_cfg)
+                    .filter(|&binding| {
+                        !self.is_binding_obstructed(&image, fixed_ref.particle, binding)
+                    })
+                    .map(|r: BindingResult| AttachmentCheckResult::Ok(fixed_ref.as_copy(), r))
+            })
+            .unwrap_or(AttachmentCheckResult::SitesBusy)
+    }
+
+    /// update attachments and particles disposition
+    pub fn update_attachments(&mut self) {
+        let clusters = if self.aggregation {
+            self.mp_container.group_by_cluster_id()
+        } else {
+            BTreeMap::new()
+        };
+        let mut converted = Vec::new();
+        for (moving, mut fixed, bind_result) in self.check_mp_attachment() {
+            // a member of a cluster may have been frozen with another member
+            if converted.contains(&moving.index) {
+                continue;
+            }
+            // symmetric images bound earlier in this batch may have taken ports
+            // of the static particle, so bind to its current state
+            match self.sp_container.at(fixed.index) {
+                Some(&current) => fixed.particle = current,
+                None => continue,
+            }
+            let others = clusters
+                .get(&moving.particle.cluster())
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&index| index != moving.index)
+                .collect::<Vec<_>>();
+            if self.convert_mp_to_static(&moving.particle, &mut fixed, bind_result, &others) {
+                self.wander_stats
+                    .record_attachment(self.time - moving.particle.since);
+                converted.push(moving.index);
+                converted.extend(others);
+            }
+        }
+
+        let attachments = converted.len();
+        self.mp_container.remove_multiple_by_index(converted);
+        self.expire_moving_particles();
+        if self.aggregation {
+            self.bond_moving_particles();
+        }
+        self.record_growth_sample(attachments);
+    }
+
+    /// add current growth metrics to telemetry
+    fn record_growth_sample(&mut self, attachments: usize) {
+        let bind_cfg = &self.bind_cfgs[0];
+        let static_count = self.sp_container.size();
+        let moving_count = self.mp_container.size();
+
+        let mut center = Vector::new(0., 0.);
+        let mut port_histogram = [0; PORT_HISTOGRAM_SIZE];
+        let mut active_sites = 0;
+        for fixed in self.sp_container.values() {
+            center += fixed.particle.pos;
+            port_histogram[fixed.particle.busy_ports().count_ones() as usize] += 1;
+            if bind_cfg.has_free_port(fixed.particle) {
+                active_sites += 1;
+            }
+        }
+        center = center * (1. / static_count.max(1) as f64);
+        let cluster_radius = self
+            .sp_container
+            .values()
+            .map(|fixed| Vector::length(&Vector::diff(&fixed.particle.pos, &center)))
+            .fold(0., f64::max);
+        let mean_wander_time = self
+            .mp_container
+            .values()
+            .map(|moving| self.time - moving.particle.since)
+            .sum::<f64>()
+            / moving_count.max(1) as f64;
+
+        self.telemetry.record(&GrowthSample {
+            time: self.time,
+            static_count,
+            moving_count,
+            attachments,
+            cluster_radius,
+            mean_wander_time,
+            active_sites,
+            port_histogram,
+        });
+    }
+
+    /// check that a cluster attaching with a binding leaves room for all its members
+    /// among static particles, and that all of them fit in their frozen positions
+    fn cluster_fits_binding(
+        &self,
+        moving: &MovingParticle,
+        fixed: &StaticParticle,
+        binding: BindingResult,
+        cluster: &[usize],
+    ) -> bool {
+        let mut anchor = *fixed;
+        match binding.apply_binding(moving, &mut anchor, &self.bind_cfgs[0], &self.bind_cfgs[0]) {
+            Some(bound) => {
+                self.sp_container.size() + 1 + cluster.len() <= self.sp_container.max_size()
+                    && self.cluster_fits(cluster, Vector::diff(&bound.pos, &moving.pos))
+            }
+            None => false,
+        }
+    }
+
+    /// check that cluster members shifted by offset stay inside the field,
+    /// clear of obstacles, forbidden regions and static particles
+    fn cluster_fits(&self, members: &[usize], offset: Vector) -> bool {
+        members
+            .iter()
+            .filter_map(|&index| self.mp_container.at(index))
+            .all(|member| {
+                let pos = self.shifted(&member.pos, offset);
+                self.boundary.contains(&pos)
+                    && !self.is_obstructed(&pos)
+                    && !self.overlaps_static(&pos)
+            })
+    }
+
+    /// check if a position is closer than binding distance to a static particle
+    /// (touching one at binding distance is fine)
+    fn overlaps_static(&self, pos: &Vector) -> bool {
+        let radius = self.bind_cfgs[0].radius();
+        let period = self.period();
+        self.sp_container
+            .select_for_binding(pos, radius)
             .into_iter()
-            .filter(|fixed| bind_cfg.close_enough_to_bind(&fixed.particle.pos, &moving.pos))
-            .collect();
-        if closest_static_particles.is_empty() {
-            return AttachmentCheckResult::NoOtherParticle;
+            .any(|fixed| {
+                let diff = match period {
+                    Some(period) => Vector::min_image_diff(pos, &fixed.particle.pos, &period),
+                    None => Vector::diff(pos, &fixed.particle.pos),
+                };
+                Vector::length(&diff) < radius * (1. - CLUSTER_CONTACT_TOLERANCE)
+            })
+    }
+
+    /// position moved by offset, wrapped around a periodic field
+    fn shifted(&self, pos: &Vector, offset: Vector) -> Vector {
+        if self.period().is_some() {
+            self.boundary.wrap(&(*pos + offset))
+        } else {
+            *pos + offset
         }
+    }
 
-        closest_static_particles
+    /// add the rest of a drifting cluster to static particles, shifted the same way
+    /// as its member that has just attached (already static at bound_index)
+    fn freeze_cluster_members(&mut self, members: &[usize], bound_index: usize, offset: Vector) {
+        let period = self.period();
+        let separation = |a: &Vector, b: &Vector| match period {
+            Some(period) => Vector::length(&Vector::min_image_diff(a, b, &period)),
+            None => Vector::length(&Vector::diff(a, b)),
+        };
+        let mut frozen = members
             .iter()
-            .find_map(|fixed_ref| {
-                BindingResult::get_binding(moving, fixed_ref.particle, bind_cfg, bind_cfg)
-                    .map_or(None, |r: BindingResult| {
-                        Some(AttachmentCheckResult::Ok(fixed_ref.as_copy(), r))
+            .filter_map(|&index| self.mp_container.at(index))
+            .map(|member| StaticParticle {
+                pos: self.shifted(&member.pos, offset),
+                ..member.as_static()
+            })
+            .collect::<Vec<_>>();
+        // members closer to the attached one go first, and are bound
+        // to the closest particle of their cluster frozen before them
+        let bound_pos = match self.sp_container.at(bound_index) {
+            Some(bound) => bound.pos,
+            None => return,
+        };
+        frozen.sort_by(|a, b| {
+            separation(&a.pos, &bound_pos)
+                .partial_cmp(&separation(&b.pos, &bound_pos))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut placed = vec![(bound_index, bound_pos)];
+        for particle in frozen {
+            let parent = placed
+                .iter()
+                .min_by(|a, b| {
+                    separation(&a.1, &particle.pos)
+                        .partial_cmp(&separation(&b.1, &particle.pos))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|&(index, _)| index);
+            let index = self.sp_container.size();
+            if self.add_bound_static_particle(particle, parent) {
+                placed.push((index, particle.pos));
+            }
+        }
+    }
+
+    /// find pairs of moving particles from different clusters that can bond
+    fn check_cluster_bonds(&self) -> Vec<(usize, usize, BindingResult)> {
+        let bind_cfg = &self.bind_cfgs[0];
+        self.mp_container
+            .values()
+            .filter_map(|moving| {
+                let cluster = moving.particle.cluster();
+                self.mp_container
+                    .select_nearby(&moving.particle.pos, bind_cfg.radius())
+                    .into_iter()
+                    .filter(|other| {
+                        other.index != moving.index
+                            && (cluster == 0 || other.particle.cluster() != cluster)
+                    })
+                    .find_map(|other| {
+                        let image = self.image_near(moving.particle, &other.particle.pos);
+                        BindingResult::get_binding(
+                            &image,
+                            &other.particle.as_static(),
+                            bind_cfg,
+                            bind_cfg,
+                        )
+                        .filter(|binding| image.busy_ports() & (1 << binding.site_at_moving()) == 0)
+                        .map(|binding| (moving.index, other.index, binding))
                     })
             })
-            .unwrap_or(AttachmentCheckResult::SitesBusy)
+            .collect()
     }
 
-    /// update attachments and particles disposition
-    pub fn update_attachments(&mut self) {
-        let converted = self
-            .check_mp_attachment()
-            .into_iter()
-            .filter_map(|(moving, mut fixed, bind_result)| {
-                if self.convert_mp_to_static(&moving.particle, &mut fixed, bind_result) {
-                    Some(moving.index)
-                } else {
-                    None
+    /// bond touching moving particles, merging their clusters
+    fn bond_moving_particles(&mut self) {
+        for (index, other_index, binding) in self.check_cluster_bonds() {
+            self.bond_to_cluster(index, other_index, binding);
+        }
+    }
+
+    /// bond a moving particle to another one: its whole cluster is shifted into
+    /// the bond position and joins the cluster of the other particle
+    fn bond_to_cluster(&mut self, index: usize, other_index: usize, binding: BindingResult) {
+        let (moving, mut other) = match (
+            self.mp_container.at(index).copied(),
+            self.mp_container.at(other_index).copied(),
+        ) {
+            (Some(moving), Some(other)) => (moving, other),
+            _ => return,
+        };
+        // clusters may have been merged by an earlier bond in this update
+        if moving.cluster() != 0 && moving.cluster() == other.cluster() {
+            return;
+        }
+        let image = self.image_near(&moving, &other.pos);
+        let mut anchor = other.as_static();
+        let bound = match binding.apply_binding(
+            &image,
+            &mut anchor,
+            &self.bind_cfgs[0],
+            &self.bind_cfgs[0],
+        ) {
+            Some(bound) => bound,
+            None => return,
+        };
+        let offset = Vector::diff(&bound.pos, &image.pos);
+        let members = match moving.cluster() {
+            0 => vec![index],
+            own => self
+                .mp_container
+                .group_by_cluster_id()
+                .remove(&own)
+                .unwrap_or_default(),
+        };
+        if !self.cluster_fits(&members, offset) {
+            return;
+        }
+        let cluster = if other.cluster() != 0 {
+            other.cluster()
+        } else {
+            let id = self.next_cluster_id;
+            self.next_cluster_id = self.next_cluster_id.checked_add(1).unwrap_or(1);
+            id
+        };
+
+        other.set_busy_ports(anchor.busy_ports());
+        other.set_cluster(cluster);
+        self.mp_container.update(&Particle {
+            particle: other,
+            index: other_index,
+        });
+
+        for member_index in members {
+            if let Some(mut member) = self.mp_container.at(member_index).copied() {
+                member.pos = self.shifted(&member.pos, offset);
+                if member_index == index {
+                    member.set_busy_ports(member.busy_ports() | bound.busy_ports());
+                    // a free particle turns to match the bond, clusters stay rigid
+                    if moving.cluster() == 0 {
+                        member.rot = bound.rot;
+                    }
                 }
-            })
-            .collect::<Vec<_>>();
+                member.set_cluster(cluster);
+                member.vel = other.vel;
+                member.speed = other.speed;
+                member.since = other.since;
+                self.mp_container.relocate(&Particle {
+                    particle: member,
+                    index: member_index,
+                });
+            }
+        }
+    }
 
-        self.mp_container.remove_multiple_by_index(converted);
+    /// remove or respawn moving particles that outlived their lifetime
+    fn expire_moving_particles(&mut self) {
+        if let Some(ttl) = self.ttl {
+            let now = self.time;
+            // clusters expire as a whole
+            let expired = self.select_with_clusters(|moving| now - moving.since > ttl);
+            let species = expired
+                .iter()
+                .filter_map(|&index| self.mp_container.at(index))
+                .map(|moving| moving.species() as u8)
+                .collect::<Vec<_>>();
+
+            self.wander_stats.expired += expired.len() as u32;
+            self.mp_container.remove_multiple_by_index(expired);
+            if self.expiry_action == ExpiryAction::Respawn {
+                for species in species {
+                    self.add_boundary_particle_of_species(now, species);
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::geometry;
     use wasm_bindgen_test::*;
 
     impl std::fmt::Debug for Vector {
@@ -362,24 +1850,36 @@ mod tests {
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
         f.mp_container.add_particle(MovingParticle {
             pos: Vector { x: -5.0, y: 1.0 },
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
         f.mp_container.add_particle(MovingParticle {
             pos: Vector { x: 2.0, y: 2.0 },
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
         f.mp_container.add_particle(MovingParticle {
             pos: Vector { x: -5.0, y: 6.0 },
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
 
         let att = f.check_mp_attachment();
@@ -393,6 +1893,76 @@ mod tests {
         assert!(att.is_empty(), "Attachments found on second pass");
     }
 
+    #[wasm_bindgen_test]
+    fn attachment_same_port() {
+        let mut f = Field::new(200., 200.);
+        f.bind_cfgs[0] = BindingConfiguration::make_square();
+        assert!(f.add_static_particle(Vector::new(0., 0.)));
+        // both particles face port 0 and are checked in one batch
+        for &(x, y) in &[(4., 0.5), (3., 1.5)] {
+            f.mp_container.add_particle(MovingParticle {
+                pos: Vector { x, y },
+                ..MovingParticle::default()
+            });
+        }
+        assert_eq!(f.check_mp_attachment().len(), 2);
+
+        f.update_attachments();
+        assert_eq!(f.static_particles_count(), 2);
+        assert_eq!(f.moving_particles_count(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn aligned_attachment() {
+        let mut f = Field::new(200., 200.);
+        f.set_attachment_alignment(AttachmentAlignment::Free);
+        f.set_aligning_field(0., 0., 6, 0.5, 0.);
+        assert!(f.add_static_particle(Vector::new(0., 0.)));
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector::new(4., 0.),
+            rot: 20.,
+            ..MovingParticle::default()
+        });
+
+        f.update_attachments();
+        assert_eq!(f.static_particles_count(), 2);
+        // half of the misalignment is removed on binding
+        let bound = f
+            .sp_container
+            .values()
+            .find(|fixed| fixed.particle.pos.x > 0.)
+            .unwrap();
+        assert!((bound.particle.rot - 10.).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test]
+    fn rotation_decides_orientation() {
+        let mut f = Field::new(200., 200.);
+        f.set_attachment_alignment(AttachmentAlignment::Free);
+        f.set_rotational_diffusion(0, 100.);
+        assert!(f.add_static_particle(Vector::new(0., 0.)));
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector::new(4., 0.),
+            speed: 0.,
+            ..MovingParticle::default()
+        });
+
+        // a still particle only turns
+        f.update_velocities(1.);
+        f.update_positions(1.);
+        let rot = f.mp_container.at(0).unwrap().rot;
+        assert!(rot != 0.);
+
+        f.update_attachments();
+        assert_eq!(f.static_particles_count(), 2);
+        let bound = f
+            .sp_container
+            .values()
+            .find(|fixed| fixed.particle.pos.x > 0.)
+            .unwrap();
+        assert_eq!(bound.particle.rot, rot);
+    }
+
     #[wasm_bindgen_test]
     fn attachment_multi() {
         let mut f = Field::new(200., 200.);
@@ -411,18 +1981,27 @@ mod tests {
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
         f.mp_container.add_particle(MovingParticle {
             pos: Vector { x: 2.0, y: 2.0 },
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
         f.mp_container.add_particle(MovingParticle {
             pos: Vector { x: -5.0, y: 6.0 },
             vel: d,
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         });
 
         let att = f.check_mp_attachment();
@@ -464,4 +2043,473 @@ mod tests {
         );
         assert_eq!(f.static_particles_count(), 4);
     }
+
+    #[wasm_bindgen_test]
+    fn seed_limits() {
+        let mut f = Field::new(200., 200.);
+        let origin = Vector::new(0., 0.);
+        // spacing below binding radius
+        assert_eq!(f.seed_circle(origin, 50., 1e-9), 0);
+        assert_eq!(f.seed_line(origin, Vector::new(50., 0.), 1.), 0);
+        assert_eq!(f.seed_polygon(origin, 50., 4, 0., 4.9), 0);
+        assert_eq!(f.seed_spiral(origin, 3., 10., 0.), 0);
+        assert_eq!(f.static_particles_count(), 0);
+
+        // huge patterns are cut off at the remaining capacity
+        for _ in 0..MAX_STATIC - 2 {
+            f.sp_container.add_particle(StaticParticle {
+                pos: Vector::new(1e6, 1e6),
+                ..StaticParticle::default()
+            });
+        }
+        assert_eq!(f.seed_circle(origin, 1e15, 5.), 0);
+        assert_eq!(f.seed_spiral(origin, 1e15, 10., 10.), 2);
+        assert_eq!(f.static_particles_count(), MAX_STATIC);
+    }
+
+    #[wasm_bindgen_test]
+    fn periodic_attachment() {
+        let mut f = Field::new(200., 200.);
+        f.bind_cfgs[0] = BindingConfiguration::make_square();
+        f.set_rectangle_boundary(200., 200.);
+        f.set_boundary_behaviour(BoundaryBehaviour::Periodic);
+        assert!(f.add_static_particle(Vector::new(199., 50.)));
+
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector { x: -198.0, y: 51.0 },
+            vel: Vector { x: -1.0, y: 0.0 },
+            since: 0.,
+            flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
+        });
+        let att = f.check_mp_attachment();
+        assert_eq!(att.len(), 1, "No attachment across the seam");
+
+        f.update_attachments();
+        assert_eq!(f.static_particles_count(), 2);
+        let bound = f.sp_container.at(1).unwrap().pos;
+        assert!(f.is_inside(bound), "Bound particle outside the field");
+        assert!(bound.x < -195., "Bound particle on the wrong side");
+
+        // moving particles wrap around
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector { x: 0.0, y: 199.5 },
+            vel: Vector { x: 0.0, y: 1.0 },
+            since: 0.,
+            flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
+        });
+        f.update_positions(1.);
+        assert!((f.mp_container.at(0).unwrap().pos.y + 199.5).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test]
+    fn periodic_needs_rectangle() {
+        let mut f = Field::new(200., 200.);
+        f.set_boundary_behaviour(BoundaryBehaviour::Periodic);
+        assert!(f.period().is_none());
+        // wrapping would leave the particle in the corner of the bounding rectangle
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector::new(140., 140.),
+            vel: Vector::new(1., 0.),
+            ..MovingParticle::default()
+        });
+        f.update_positions(3.);
+        let particle = f.mp_container.at(0).unwrap();
+        assert!(f.is_inside(particle.pos));
+        assert!(particle.vel.x < 0.);
+
+        f.set_annulus_boundary(50., 150.);
+        assert!(f.period().is_none());
+        f.set_rectangle_boundary(200., 200.);
+        assert!(f.period().is_some());
+    }
+
+    #[wasm_bindgen_test]
+    fn deposition() {
+        let mut f = Field::new(200., 200.);
+        f.set_rectangle_boundary(200., 200.);
+        f.set_spawn_region(SpawnRegion::Top);
+        f.set_gravity(Vector::new(0., 1.));
+        f.set_attractor_strength(0.);
+        assert!(f.seed_substrate(2., 10.) > 0);
+
+        for _ in 0..100 {
+            f.add_boundary_particle(0.);
+        }
+        assert_eq!(f.moving_particles_count(), 100);
+        assert!((0..100).all(|i| f.mp_container.at(i).unwrap().pos.y <= -180.));
+
+        for _ in 0..20 {
+            f.update_velocities(1.);
+        }
+        let mean_vy = (0..100)
+            .map(|i| f.mp_container.at(i).unwrap().vel.y)
+            .sum::<f64>()
+            / 100.;
+        assert!(mean_vy > 0.5, "Particles do not fall: {}", mean_vy);
+    }
+
+    #[wasm_bindgen_test]
+    fn lifetime() {
+        let mut f = Field::new(200., 200.);
+        for _ in 0..10 {
+            f.add_boundary_particle_of_species(0., 1);
+        }
+        f.set_lifetime(5., ExpiryAction::Respawn);
+        f.set_time(4.);
+        f.update_attachments();
+        assert_eq!(f.wander_statistics().expired, 0);
+
+        f.set_time(10.);
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 10);
+        assert_eq!(f.wander_statistics().expired, 10);
+        assert!((0..10).all(|i| {
+            let p = f.mp_container.at(i).unwrap();
+            p.since == 10. && p.species() == 1
+        }));
+
+        f.set_lifetime(5., ExpiryAction::Remove);
+        f.set_time(20.);
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 0);
+        assert_eq!(f.wander_statistics().expired, 20);
+
+        // particles added later live from the moment they were added
+        f.add_particle();
+        f.set_time(21.);
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 1);
+        assert_eq!(f.mp_container.at(0).unwrap().since, 20.);
+
+        // a cluster expires with its oldest member
+        f.mp_container.remove_multiple_by_index(vec![0]);
+        for &since in &[14., 20.] {
+            let mut particle = MovingParticle {
+                since,
+                ..MovingParticle::default()
+            };
+            particle.set_cluster(7);
+            f.mp_container.add_particle(particle);
+        }
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn particle_flags() {
+        let mut f = Field::new(200., 200.);
+        assert!(f.add_particle_at(Vector::new(10., 10.), 0));
+        assert!(f.add_particle_at(Vector::new(-10., 10.), 2));
+        assert!(!f.add_particle_at(Vector::new(300., 0.), 0));
+        assert_eq!(f.particles_with_flag(ParticleFlag::Seeded), vec![0, 1]);
+
+        assert!(f.set_particle_flag(1, ParticleFlag::Frozen, true));
+        assert!(!f.set_particle_flag(2, ParticleFlag::Frozen, true));
+        assert_eq!(f.particles_with_flag(ParticleFlag::Frozen), vec![1]);
+
+        f.update_velocities(1.);
+        f.update_positions(1.);
+        assert!(f.mp_container.at(0).unwrap().pos != Vector::new(10., 10.));
+        assert!(f.mp_container.at(1).unwrap().pos == Vector::new(-10., 10.));
+        assert_eq!(f.mp_container.at(1).unwrap().species(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn trapped_by_obstacle() {
+        let mut f = Field::new(200., 200.);
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector { x: 0., y: 0. },
+            vel: Vector { x: 1., y: 0. },
+            ..MovingParticle::default()
+        });
+        // obstacle placed on top of the particle
+        f.add_circle_obstacle(Vector::new(1., 0.), 5.);
+        f.update_positions(0.1);
+        let pos = f.mp_container.at(0).unwrap().pos;
+        assert!(!f.is_obstructed(&pos), "Still inside at {}", pos.x);
+        assert!(pos.x < -4.);
+    }
+
+    #[wasm_bindgen_test]
+    fn hard_core_exclusion() {
+        let mut f = Field::new(200., 200.);
+        f.set_hard_core_exclusion(2.);
+        for &(x, vx) in &[(0., 1.), (3., -1.)] {
+            f.mp_container.add_particle(MovingParticle {
+                pos: Vector { x, y: 0. },
+                vel: Vector { x: vx, y: 0. },
+                ..MovingParticle::default()
+            });
+        }
+        for _ in 0..3 {
+            f.update_positions(1.);
+            let distance = Vector::length(&Vector::diff(
+                &f.mp_container.at(1).unwrap().pos,
+                &f.mp_container.at(0).unwrap().pos,
+            ));
+            assert!(distance >= 2., "Particles overlap at {}", distance);
+        }
+        assert!(f.mp_container.at(0).unwrap().pos == Vector::new(1., 0.));
+
+        f.clear_repulsion();
+        f.update_positions(1.);
+        assert!(f.mp_container.at(0).unwrap().pos == Vector::new(2., 0.));
+    }
+
+    #[wasm_bindgen_test]
+    fn cluster_aggregation() {
+        let mut f = Field::new(200., 200.);
+        f.set_cluster_aggregation(true);
+        for &x in &[0., 3.] {
+            f.mp_container.add_particle(MovingParticle {
+                pos: Vector { x, y: 0. },
+                ..MovingParticle::default()
+            });
+        }
+        f.update_attachments();
+        assert_eq!(f.moving_clusters_count(), 1);
+        let (first, second) = (
+            *f.mp_container.at(0).unwrap(),
+            *f.mp_container.at(1).unwrap(),
+        );
+        assert!(first.cluster() != 0 && first.cluster() == second.cluster());
+        assert!((Vector::length(&Vector::diff(&second.pos, &first.pos)) - 5.).abs() < 1e-9);
+
+        // the whole cluster freezes when one member attaches
+        assert!(f.add_static_particle(Vector::new(first.pos.x, -4.)));
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 0);
+        assert_eq!(f.static_particles_count(), 3);
+        let (a, b) = (
+            *f.sp_container.at(1).unwrap(),
+            *f.sp_container.at(2).unwrap(),
+        );
+        assert!((Vector::length(&Vector::diff(&b.pos, &a.pos)) - 5.).abs() < 1e-9);
+        assert!(a.busy_ports() != 0 && b.busy_ports() != 0);
+        assert_eq!(f.bond_tree.parent(1), Some(0));
+        assert_eq!(f.bond_tree.parent(2), Some(1));
+    }
+
+    #[wasm_bindgen_test]
+    fn cluster_motion() {
+        let mut f = Field::new(200., 200.);
+        f.set_cluster_aggregation(true);
+        // a free particle and a cluster of two with different speeds
+        for &(y, speed, cluster) in &[(-20., 1., 0), (0., 1., 3), (5., 3., 3)] {
+            let mut particle = MovingParticle {
+                pos: Vector { x: 0., y },
+                vel: Vector { x: 1., y: 0. },
+                speed,
+                ..MovingParticle::default()
+            };
+            particle.set_cluster(cluster);
+            f.mp_container.add_particle(particle);
+        }
+        f.update_positions(1.);
+        let x = |f: &Field, index: usize| f.mp_container.at(index).unwrap().pos.x;
+        assert_eq!(x(&f, 0), 1.);
+        // mean speed 2, slowed down by 2^-0.5, the same for both members
+        assert!((x(&f, 1) - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(x(&f, 1), x(&f, 2));
+
+        f.set_cluster_mobility_exponent(0.);
+        f.update_positions(1.);
+        assert!((x(&f, 1) - 2f64.sqrt() - 2.).abs() < 1e-12);
+
+        // a frozen member holds the whole cluster
+        f.set_particle_flag(2, ParticleFlag::Frozen, true);
+        f.update_positions(1.);
+        assert_eq!(x(&f, 1), x(&f, 2));
+        assert!((x(&f, 1) - 2f64.sqrt() - 2.).abs() < 1e-12);
+        f.set_particle_flag(2, ParticleFlag::Frozen, false);
+
+        // a member about to hit an obstacle turns the whole cluster back
+        f.add_segment_obstacle(Vector::new(5., 3.), Vector::new(5., 10.));
+        f.update_positions(1.);
+        assert_eq!(x(&f, 1), x(&f, 2));
+        assert!(f.mp_container.at(1).unwrap().vel == Vector::new(-1., 0.));
+
+        // a cluster that would freeze inside an obstacle does not attach
+        f.add_circle_obstacle(Vector::new(x(&f, 2), 9.), 5.);
+        let cluster_start = f.mp_container.at(1).unwrap().pos;
+        assert!(f.add_static_particle(cluster_start + Vector::new(0., -5.)));
+        f.update_attachments();
+        assert_eq!(f.static_particles_count(), 1);
+        assert_eq!(f.moving_particles_count(), 3);
+    }
+
+    #[wasm_bindgen_test]
+    fn cluster_contacts() {
+        // crystal along the x axis with ports facing the axes
+        let crystal = |extra: Option<Vector>| {
+            let mut f = Field::new(200., 200.);
+            f.bind_cfgs[0] = BindingConfiguration::make_square();
+            f.set_cluster_aggregation(true);
+            let mut parent = None;
+            for pos in [Vector::new(0., 0.), Vector::new(5., 0.)]
+                .iter()
+                .chain(extra.iter())
+            {
+                let particle = StaticParticle {
+                    pos: *pos,
+                    rot: -45.,
+                    binding_cfg_id: 0,
+                };
+                assert!(f.add_bound_static_particle(particle, parent));
+                parent = Some(0);
+            }
+            // L-shaped cluster attaching above (0, 0); its corner ends up
+            // at binding distance above (5, 0), touching the crystal there too
+            for &(x, y) in &[(0., 4.), (5., 4.), (5., 9.)] {
+                let mut particle = MovingParticle {
+                    pos: Vector { x, y },
+                    ..MovingParticle::default()
+                };
+                particle.set_cluster(1);
+                f.mp_container.add_particle(particle);
+            }
+            f
+        };
+
+        let mut f = crystal(None);
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 0);
+        assert_eq!(f.static_particles_count(), 5);
+        let corner = f.sp_container.values().find(|fixed| {
+            Vector::length(&Vector::diff(&fixed.particle.pos, &Vector::new(5., 5.))) < 1e-9
+        });
+        assert!(corner.is_some());
+
+        // the corner would be placed right next to another static particle
+        let mut f = crystal(Some(Vector::new(5., 6.)));
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 3);
+        assert_eq!(f.static_particles_count(), 3);
+    }
+
+    #[wasm_bindgen_test]
+    fn growth_telemetry() {
+        let mut f = Field::new(200., 200.);
+        assert!(f.add_static_particle(Vector::new(0., 0.)));
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector { x: 4., y: 0. },
+            ..MovingParticle::default()
+        });
+        f.set_time(3.);
+        f.update_attachments();
+        assert_eq!(f.telemetry_len(), 1);
+        // the buffer behind `telemetry_view`
+        let buffer = f.telemetry.as_slice();
+        assert_eq!(buffer.len(), f.telemetry_capacity() * f.telemetry_stride());
+        let sample = &buffer[..f.telemetry_stride()];
+        // time, static, moving, attachments, radius, wander time, active sites
+        assert_eq!(sample[..7], [3., 2., 0., 1., 2.5, 0., 2.]);
+        // both particles have one busy port
+        assert_eq!(sample[7..10], [0., 2., 0.]);
+    }
+
+    #[wasm_bindgen_test]
+    fn crystal_components() {
+        let mut f = Field::new(200., 200.);
+        // two crystals touching, but not bound to each other
+        for &(x, parent) in &[(0., None), (5., Some(0)), (9., None), (13., Some(2))] {
+            let particle = StaticParticle {
+                pos: Vector { x, y: 0. },
+                rot: 0.,
+                binding_cfg_id: 0,
+            };
+            assert!(f.add_bound_static_particle(particle, parent));
+        }
+        assert!(f.add_static_particle(Vector::new(100., 100.)));
+        assert_eq!(f.bond_segments_count(), 2);
+        assert!(
+            f.bonds[1]
+                == BondSegment {
+                    from: Vector::new(9., 0.),
+                    to: Vector::new(13., 0.),
+                    port: 0.,
+                    generation: 1.,
+                }
+        );
+
+        let bonds = f.components(Connectivity::Bonds);
+        assert_eq!(bonds.labels(), vec![0, 0, 1, 1, 2]);
+        assert_eq!(bonds.sizes(), vec![2, 2, 1]);
+        assert_eq!(bonds.centroids()[..4], [2.5, 0., 11., 0.]);
+        let proximity = f.components(Connectivity::Proximity);
+        assert_eq!(proximity.labels(), vec![0, 0, 0, 0, 1]);
+        assert_eq!(proximity.bounding_boxes()[..4], [0., 0., 13., 0.]);
+
+        let hulls = f.convex_hull_outlines(Connectivity::Proximity);
+        assert_eq!(hulls, vec![2., 0., 0., 13., 0., 1., 100., 100.]);
+    }
+
+    #[wasm_bindgen_test]
+    fn annulus_tessellation() {
+        let mut f = Field::new(200., 200.);
+        f.set_annulus_boundary(50., 150.);
+        for &(x, y) in &[(100., 0.), (0., 100.), (-100., 0.), (0., -100.)] {
+            f.sp_container.add_particle(StaticParticle {
+                pos: Vector::new(x, y),
+                ..StaticParticle::default()
+            });
+        }
+        // the cells cover the whole outer circle, hole included
+        let cells = f.tessellation().voronoi_cells();
+        let mut rest = &cells[..];
+        let (mut total_area, mut in_hole) = (0f64, false);
+        while let Some((&n, tail)) = rest.split_first() {
+            let (cell, next) = tail.split_at(2 * n as usize);
+            let vertices = cell
+                .chunks(2)
+                .map(|v| Vector::new(v[0], v[1]))
+                .collect::<Vec<_>>();
+            in_hole |= geometry::polygon_contains(&vertices, &Vector::new(10., 5.));
+            total_area += geometry::polygon_signed_area(&vertices);
+            rest = next;
+        }
+        assert!(in_hole);
+        let outer = f.boundary.outline(BOUNDARY_OUTLINE_SEGMENTS);
+        assert!((total_area - geometry::polygon_signed_area(&outer)).abs() < 1e-6);
+    }
+
+    #[wasm_bindgen_test]
+    fn symmetric_growth() {
+        let mut f = Field::new(200., 200.);
+        f.bind_cfgs[0] = BindingConfiguration::make_square();
+        f.bind_cfgs[0].set_max_binds(4);
+        f.set_symmetry(4, false, Vector::new(0., 0.));
+        assert!(f.add_static_particle(Vector::new(0., 0.)));
+        // the center is fixed by every rotation, so it has no images to add
+        assert!(f.symmetry.unwrap().images(&Vector::new(0., 0.)).is_empty());
+        assert_eq!(f.static_particles_count(), 1);
+
+        f.mp_container.add_particle(MovingParticle {
+            pos: Vector { x: 4.0, y: 1.0 },
+            vel: Vector { x: 1.0, y: 0.0 },
+            since: 0.,
+            flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
+        });
+        f.update_attachments();
+        assert_eq!(f.moving_particles_count(), 0);
+        assert_eq!(f.static_particles_count(), 5);
+
+        let near = |x: f64, y: f64| {
+            (0..f.static_particles_count()).any(|i| {
+                let pos = f.sp_container.at(i).unwrap().pos;
+                (pos.x - x).abs() < 1e-6 && (pos.y - y).abs() < 1e-6
+            })
+        };
+        let r = std::f64::consts::FRAC_1_SQRT_2 * 5.;
+        assert!(near(r, r) && near(-r, r) && near(-r, -r) && near(r, -r));
+    }
 }
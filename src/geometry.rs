@@ -0,0 +1,223 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::vector::Vector;
+
+/// Polygon edges as (start, end) pairs, including the closing edge
+pub fn polygon_edges(vertices: &[Vector]) -> impl Iterator<Item = (Vector, Vector)> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(&a, &b)| (a, b))
+}
+
+/// Point of a segment closest to a position
+pub fn closest_point_on_segment(pos: &Vector, a: &Vector, b: &Vector) -> Vector {
+    let edge = Vector::diff(b, a);
+    let len_sq = Vector::dot(&edge, &edge);
+    if len_sq == 0. {
+        return *a;
+    }
+    let t = (Vector::dot(&Vector::diff(pos, a), &edge) / len_sq).clamp(0., 1.);
+    *a + edge * t
+}
+
+/// Check if two segments have a common point
+pub fn segments_intersect(a1: &Vector, a2: &Vector, b1: &Vector, b2: &Vector) -> bool {
+    // orientation of point c relative to directed line p->q
+    let orientation =
+        |p: &Vector, q: &Vector, c: &Vector| (q.x - p.x) * (c.y - p.y) - (q.y - p.y) * (c.x - p.x);
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+    if ((d1 > 0. && d2 < 0.) || (d1 < 0. && d2 > 0.))
+        && ((d3 > 0. && d4 < 0.) || (d3 < 0. && d4 > 0.))
+    {
+        return true;
+    }
+    // collinear and touching cases
+    let on_segment = |p: &Vector, a: &Vector, b: &Vector| {
+        Vector::distance_squared(&closest_point_on_segment(p, a, b), p) == 0.
+    };
+    (d1 == 0. && on_segment(a1, b1, b2))
+        || (d2 == 0. && on_segment(a2, b1, b2))
+        || (d3 == 0. && on_segment(b1, a1, a2))
+        || (d4 == 0. && on_segment(b2, a1, a2))
+}
+
+/// Point of a polygon outline closest to a position, with index of its edge
+pub fn closest_polygon_edge(vertices: &[Vector], pos: &Vector) -> (usize, Vector) {
+    polygon_edges(vertices)
+        .map(|(a, b)| closest_point_on_segment(pos, &a, &b))
+        .enumerate()
+        .fold((0, vertices[0]), |best, (index, point)| {
+            if index == 0
+                || Vector::distance_squared(&point, pos) < Vector::distance_squared(&best.1, pos)
+            {
+                (index, point)
+            } else {
+                best
+            }
+        })
+}
+
+/// Even-odd rule point in polygon test
+pub fn polygon_contains(vertices: &[Vector], pos: &Vector) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon_edges(vertices) {
+        if (a.y > pos.y) != (b.y > pos.y) && pos.x < a.x + (pos.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Polygon area, positive for counter-clockwise vertex order
+pub fn polygon_signed_area(vertices: &[Vector]) -> f64 {
+    polygon_edges(vertices)
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum::<f64>()
+        / 2.
+}
+
+/// Static shape that moving particles bounce off and crystals cannot grow through
+#[derive(Clone, PartialEq)]
+pub enum Obstacle {
+    /// line segment between two points
+    Segment(Vector, Vector),
+    /// solid circle
+    Circle { center: Vector, radius: f64 },
+    /// solid simple polygon, vertices in order
+    Polygon(Vec<Vector>),
+}
+
+impl Obstacle {
+    /// bounding box corners (min, max)
+    pub fn bounding_box(&self) -> (Vector, Vector) {
+        match self {
+            Obstacle::Segment(a, b) => (
+                Vector::new(a.x.min(b.x), a.y.min(b.y)),
+                Vector::new(a.x.max(b.x), a.y.max(b.y)),
+            ),
+            Obstacle::Circle { center, radius } => (
+                Vector::new(center.x - radius, center.y - radius),
+                Vector::new(center.x + radius, center.y + radius),
+            ),
+            Obstacle::Polygon(vertices) => {
+                vertices
+                    .iter()
+                    .skip(1)
+                    .fold((vertices[0], vertices[0]), |(min, max), v| {
+                        (
+                            Vector::new(min.x.min(v.x), min.y.min(v.y)),
+                            Vector::new(max.x.max(v.x), max.y.max(v.y)),
+                        )
+                    })
+            }
+        }
+    }
+
+    /// check if a position is inside the obstacle (segments have no inside)
+    pub fn contains(&self, pos: &Vector) -> bool {
+        match self {
+            Obstacle::Segment(..) => false,
+            Obstacle::Circle { center, radius } => {
+                Vector::distance_squared(center, pos) < radius * radius
+            }
+            Obstacle::Polygon(vertices) => polygon_contains(vertices, pos),
+        }
+    }
+
+    /// check if a path between two positions touches the obstacle
+    pub fn blocks(&self, from: &Vector, to: &Vector) -> bool {
+        self.contains(to)
+            || match self {
+                Obstacle::Segment(a, b) => segments_intersect(from, to, a, b),
+                Obstacle::Circle { center, radius } => {
+                    Vector::distance_squared(&closest_point_on_segment(center, from, to), center)
+                        < radius * radius
+                }
+                Obstacle::Polygon(vertices) => {
+                    polygon_edges(vertices).any(|(a, b)| segments_intersect(from, to, &a, &b))
+                }
+            }
+    }
+
+    /// point of the obstacle surface closest to a position
+    pub fn closest_surface_point(&self, pos: &Vector) -> Vector {
+        match self {
+            Obstacle::Segment(a, b) => closest_point_on_segment(pos, a, b),
+            Obstacle::Circle { center, radius } => {
+                let radial = Vector::diff(pos, center);
+                let length = Vector::length(&radial);
+                if length == 0. {
+                    return *center + Vector::new(*radius, 0.);
+                }
+                *center + radial * (radius / length)
+            }
+            Obstacle::Polygon(vertices) => closest_polygon_edge(vertices, pos).1,
+        }
+    }
+
+    /// unit normal of the obstacle surface closest to a position, pointing towards it
+    pub fn normal(&self, pos: &Vector) -> Vector {
+        let closest = self.closest_surface_point(pos);
+        let outwards = Vector::diff(pos, &closest);
+        let length = Vector::length(&outwards);
+        if length == 0. {
+            return Vector::new(1., 0.);
+        }
+        // from inside a solid shape, the way out is the opposite direction
+        let sign = if self.contains(pos) { -1. } else { 1. };
+        outwards * (sign / length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn obstacle_blocking() {
+        let wall = Obstacle::Segment(Vector::new(0., -10.), Vector::new(0., 10.));
+        assert!(wall.blocks(&Vector::new(-1., 0.), &Vector::new(1., 0.)));
+        assert!(!wall.blocks(&Vector::new(-1., 11.), &Vector::new(1., 11.)));
+        assert!(wall.normal(&Vector::new(-3., 2.)) == Vector::new(-1., 0.));
+
+        let disc = Obstacle::Circle {
+            center: Vector::new(0., 0.),
+            radius: 2.,
+        };
+        assert!(disc.blocks(&Vector::new(-3., 0.), &Vector::new(3., 0.)));
+        assert!(disc.contains(&Vector::new(1., 1.)));
+        assert!(disc.normal(&Vector::new(0., 5.)) == Vector::new(0., 1.));
+
+        let triangle = Obstacle::Polygon(vec![
+            Vector::new(0., 0.),
+            Vector::new(4., 0.),
+            Vector::new(0., 4.),
+        ]);
+        assert!(triangle.contains(&Vector::new(1., 1.)));
+        assert!(!triangle.contains(&Vector::new(3., 3.)));
+        assert!(triangle.blocks(&Vector::new(-1., 1.), &Vector::new(1., 1.)));
+        assert!(triangle.normal(&Vector::new(2., -1.)) == Vector::new(0., -1.));
+        let (min, max) = triangle.bounding_box();
+        assert!(min == Vector::new(0., 0.) && max == Vector::new(4., 4.));
+    }
+}
@@ -0,0 +1,314 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+use crate::extfn;
+use crate::geometry;
+use crate::vector::Vector;
+
+/// Points closer than probe radius minus this are inside the probe
+const PROBE_EPSILON: f64 = 1e-9;
+
+/// z component of cross product (a - o) x (b - o), positive for a left turn
+fn cross(o: &Vector, a: &Vector, b: &Vector) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+/// Convex hull, counter-clockwise, without collinear points (monotone chain)
+pub fn convex_hull(points: &[Vector]) -> Vec<Vector> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| {
+        (a.x, a.y)
+            .partial_cmp(&(b.x, b.y))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<Vector> = Vec::with_capacity(sorted.len() + 1);
+    // drop points that do not make a left turn, down to a floor index
+    let push = |hull: &mut Vec<Vector>, p: Vector, floor: usize| {
+        while hull.len() >= floor + 2
+            && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], &p) <= 0.
+        {
+            hull.pop();
+        }
+        hull.push(p);
+    };
+    // lower chain left to right, then upper chain right to left
+    for &p in &sorted {
+        push(&mut hull, p, 0);
+    }
+    let floor = hull.len() - 1;
+    for &p in sorted.iter().rev().skip(1) {
+        push(&mut hull, p, floor);
+    }
+    // upper chain ends at the first point
+    hull.pop();
+    hull
+}
+
+/// Grid of point indices with cells of a given size
+struct PointGrid {
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl PointGrid {
+    fn new(points: &[Vector], cell: f64) -> Self {
+        let mut grid = Self {
+            cell,
+            cells: HashMap::new(),
+        };
+        for (index, p) in points.iter().enumerate() {
+            let key = grid.key(p);
+            grid.cells.entry(key).or_default().push(index);
+        }
+        grid
+    }
+
+    fn key(&self, p: &Vector) -> (i64, i64) {
+        (
+            (p.x / self.cell).floor() as i64,
+            (p.y / self.cell).floor() as i64,
+        )
+    }
+
+    /// indices of points in the cell of p and its 8 neighbours
+    fn around(&self, p: &Vector) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = self.key(p);
+        (-1..=1)
+            .flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+            .filter_map(move |key| self.cells.get(&key))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Concave outline: boundary of the alpha shape, counter-clockwise.
+/// An edge is on the boundary if a disc of radius alpha touching both its ends
+/// holds no other points; large alpha gives the convex hull. If the shape has
+/// holes or falls apart, the loop enclosing the largest area is returned;
+/// one particle wide branches are outlined by walking along both their sides
+pub fn alpha_shape(points: &[Vector], alpha: f64) -> Vec<Vector> {
+    if points.len() < 3 || alpha <= 0. {
+        return points.to_vec();
+    }
+    let grid = PointGrid::new(points, 2. * alpha);
+    let is_empty_probe = |center: &Vector, ends: (usize, usize)| {
+        grid.around(center).all(|index| {
+            index == ends.0
+                || index == ends.1
+                || Vector::length(&Vector::diff(&points[index], center)) >= alpha - PROBE_EPSILON
+        })
+    };
+
+    // boundary edges, directed with the shape on the left
+    let mut outgoing = HashMap::<usize, Vec<usize>>::new();
+    for (a, pa) in points.iter().enumerate() {
+        for b in grid.around(pa).filter(|&b| b > a) {
+            let pb = &points[b];
+            let chord = Vector::diff(pb, pa);
+            let length = Vector::length(&chord);
+            if length == 0. || length > 2. * alpha {
+                continue;
+            }
+            let middle = (*pa + *pb) * 0.5;
+            let left = Vector::new(-chord.y, chord.x) * (1. / length);
+            let height = (alpha * alpha - length * length / 4.).max(0.).sqrt();
+            if is_empty_probe(&(middle + left * height), (a, b)) {
+                outgoing.entry(b).or_default().push(a);
+            }
+            if is_empty_probe(&(middle + left * -height), (a, b)) {
+                outgoing.entry(a).or_default().push(b);
+            }
+        }
+    }
+
+    // after arriving at a vertex, leave by the edge turning most clockwise, so that
+    // one particle wide branches (with edges both ways) are walked around, not cut off
+    let successor = |previous: usize, current: usize| {
+        let (p, c) = (&points[previous], &points[current]);
+        let back = extfn::atan2(p.y - c.y, p.x - c.x);
+        let turn = |next: usize| {
+            let n = &points[next];
+            let angle = (extfn::atan2(n.y - c.y, n.x - c.x) - back).rem_euclid(2. * PI);
+            // going back is the last resort
+            if angle == 0. {
+                2. * PI
+            } else {
+                angle
+            }
+        };
+        outgoing.get(&current).and_then(|targets| {
+            targets.iter().copied().min_by(|&a, &b| {
+                turn(a)
+                    .partial_cmp(&turn(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        })
+    };
+
+    // every edge is walked once; a walk ends when it comes back to an edge
+    // already walked, normally its first one
+    let mut used = HashSet::<(usize, usize)>::new();
+    let mut best: Vec<usize> = Vec::new();
+    let mut best_area = f64::NEG_INFINITY;
+    let mut starts = outgoing.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+    for start in starts {
+        for &first in &outgoing[&start] {
+            if !used.insert((start, first)) {
+                continue;
+            }
+            let mut walk = vec![start];
+            let (mut previous, mut current) = (start, first);
+            while let Some(next) =
+                successor(previous, current).filter(|&next| !used.contains(&(current, next)))
+            {
+                walk.push(current);
+                used.insert((current, next));
+                previous = current;
+                current = next;
+            }
+            if current != start {
+                walk.push(current);
+            }
+            let vertices = walk.iter().map(|&index| points[index]).collect::<Vec<_>>();
+            let area = geometry::polygon_signed_area(&vertices);
+            if area > best_area || (area == best_area && walk.len() > best.len()) {
+                best_area = area;
+                best = walk;
+            }
+        }
+    }
+    best.into_iter().map(|index| points[index]).collect()
+}
+
+/// Outlines as a flat list: vertex count, then x and y of every vertex,
+/// for every outline in turn
+pub fn flatten_outlines(outlines: &[Vec<Vector>]) -> Vec<f64> {
+    let mut flat = Vec::new();
+    for outline in outlines {
+        flat.push(outline.len() as f64);
+        for p in outline {
+            flat.extend_from_slice(&[p.x, p.y]);
+        }
+    }
+    flat
+}
+
+/// SVG document with flat outlines (see `flatten_outlines`) as closed paths,
+/// in field coordinates from -dimensions to +dimensions
+pub fn outlines_to_svg(flat: &[f64], dimensions: &Vector) -> String {
+    let mut svg = String::new();
+    // writing to a String does not fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        -dimensions.x,
+        -dimensions.y,
+        2. * dimensions.x,
+        2. * dimensions.y
+    );
+    let mut rest = flat;
+    while let Some((&count, tail)) = rest.split_first() {
+        let coords = (count as usize * 2).min(tail.len());
+        let (outline, next) = tail.split_at(coords);
+        if outline.len() >= 4 {
+            let mut path = String::new();
+            for (index, point) in outline.chunks(2).enumerate() {
+                let command = if index == 0 { 'M' } else { 'L' };
+                let _ = write!(path, "{}{} {} ", command, point[0], point[1]);
+            }
+            let _ = writeln!(
+                svg,
+                r#"<path d="{}Z" fill="none" stroke="black" stroke-width="1"/>"#,
+                path
+            );
+        }
+        rest = next;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn outlines() {
+        // 10 x 10 grid with a 4 x 5 notch cut from the top
+        let points = (0..10)
+            .flat_map(|x| (0..10).map(move |y| (x, y)))
+            .filter(|&(x, y)| !((3..=6).contains(&x) && y >= 5))
+            .map(|(x, y)| Vector::new(x as f64, y as f64))
+            .collect::<Vec<_>>();
+
+        let hull = convex_hull(&points);
+        assert!(
+            hull == vec![
+                Vector::new(0., 0.),
+                Vector::new(9., 0.),
+                Vector::new(9., 9.),
+                Vector::new(0., 9.)
+            ]
+        );
+
+        let concave = alpha_shape(&points, 1.);
+        assert!((geometry::polygon_signed_area(&concave) - 57.).abs() < 1e-9);
+        let loose = alpha_shape(&points, 100.);
+        assert!((geometry::polygon_signed_area(&loose) - 81.).abs() < 1e-9);
+
+        let flat = flatten_outlines(&[hull, vec![Vector::new(1., 2.)]]);
+        assert_eq!(flat[..3], [4., 0., 0.]);
+        assert_eq!(flat[9..], [1., 1., 2.]);
+        let svg = outlines_to_svg(&flat, &Vector::new(10., 10.));
+        assert!(svg.contains(r#"viewBox="-10 -10 20 20""#));
+        assert!(svg.contains(r#"d="M0 0 L9 0 L9 9 L0 9 Z""#));
+        assert_eq!(svg.matches("<path").count(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn thin_branches() {
+        // a straight one particle wide branch is walked along both sides
+        let line = (0..10)
+            .map(|x| Vector::new(x as f64, 0.))
+            .collect::<Vec<_>>();
+        let outline = alpha_shape(&line, 2.);
+        assert_eq!(outline.len(), 18);
+        assert!(line.iter().all(|p| outline.contains(p)));
+
+        // 5 x 5 blob with an arm sticking out of its right side
+        let mut blob = (-2..=2)
+            .flat_map(|x| (-2..=2).map(move |y| Vector::new(x as f64, y as f64)))
+            .collect::<Vec<_>>();
+        blob.extend((3..=10).map(|x| Vector::new(x as f64, 0.)));
+        let outline = alpha_shape(&blob, 1.);
+        assert!(outline.contains(&Vector::new(10., 0.)));
+        // the arm is joined to the blob by diagonal edges
+        assert!((geometry::polygon_signed_area(&outline) - 17.).abs() < 1e-9);
+        // 15 blob vertices, the arm walked out (8) and back (7)
+        assert_eq!(outline.len(), 30);
+    }
+}
@@ -0,0 +1,172 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::mem::{size_of, size_of_val};
+use wasm_bindgen::prelude::*;
+
+/// How a buffer slot should be read
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SlotKind {
+    /// f64 value
+    Float,
+    /// u64 bit field, read through an integer view of the same memory
+    Bits,
+}
+
+/// Names and positions of values in a renderer-facing buffer,
+/// in f64-sized (8 byte) slots
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct BufferLayout {
+    /// slots per record
+    pub stride: usize,
+    names: Vec<&'static str>,
+    offsets: Vec<usize>,
+    kinds: Vec<SlotKind>,
+}
+
+#[wasm_bindgen]
+impl BufferLayout {
+    pub fn fields(&self) -> usize {
+        self.names.len()
+    }
+    pub fn name(&self, field: usize) -> Option<String> {
+        self.names.get(field).map(|name| name.to_string())
+    }
+    pub fn offset(&self, field: usize) -> Option<usize> {
+        self.offsets.get(field).copied()
+    }
+    pub fn kind(&self, field: usize) -> Option<SlotKind> {
+        self.kinds.get(field).copied()
+    }
+    /// slot of a named field within a record
+    pub fn offset_of(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .position(|&field| field == name)
+            .map(|field| self.offsets[field])
+    }
+}
+
+impl BufferLayout {
+    /// layout of records of type T from (name, offset, kind) of every slot
+    pub fn new<T: BufferRecord>(fields: &[(&'static str, usize, SlotKind)]) -> Self {
+        Self {
+            stride: size_of::<T>() / size_of::<f64>(),
+            names: fields.iter().map(|field| field.0).collect(),
+            offsets: fields.iter().map(|field| field.1).collect(),
+            kinds: fields.iter().map(|field| field.2).collect(),
+        }
+    }
+}
+
+/// A `#[repr(C)]` struct made of 8 byte values only, so that an array of
+/// records can be read as an array of f64
+///
+/// # Safety
+/// Implementors must be `#[repr(C)]` and hold nothing but f64, u64 and
+/// `#[repr(C)]` structs of those
+pub unsafe trait BufferRecord: Copy {
+    fn layout() -> BufferLayout;
+}
+
+/// slot of a field of a record
+pub fn slot<T, F>(record: &T, field: &F) -> usize {
+    (field as *const F as usize - record as *const T as usize) / size_of::<f64>()
+}
+
+/// records as a flat list of slots
+pub fn as_slots<T: BufferRecord>(records: &[T]) -> &[f64] {
+    // SAFETY: records are 8 byte aligned and made of 8 byte values only,
+    // any bit pattern is a valid f64
+    unsafe {
+        std::slice::from_raw_parts(
+            records.as_ptr() as *const f64,
+            size_of_val(records) / size_of::<f64>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::{BondSegment, MovingParticle, StaticParticle};
+    use crate::vector::Vector;
+    use wasm_bindgen_test::*;
+
+    fn names(layout: &BufferLayout) -> Vec<String> {
+        (0..layout.fields())
+            .filter_map(|field| layout.name(field))
+            .collect()
+    }
+
+    /// every slot of a record is described exactly once, in memory order
+    fn assert_dense(layout: &BufferLayout) {
+        let offsets = (0..layout.fields())
+            .filter_map(|field| layout.offset(field))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, (0..layout.stride).collect::<Vec<_>>());
+    }
+
+    #[wasm_bindgen_test]
+    fn record_layouts() {
+        assert_eq!(size_of::<Vector>(), 16);
+
+        let moving = MovingParticle::layout();
+        assert_eq!(size_of::<MovingParticle>(), 72);
+        assert_eq!(MovingParticle::get_f64_size(), 9);
+        assert_dense(&moving);
+        assert_eq!(
+            names(&moving),
+            ["pos.x", "pos.y", "vel.x", "vel.y", "since", "flags", "speed", "rot", "spin"]
+        );
+        assert_eq!(moving.offset_of("flags"), Some(5));
+        assert_eq!(moving.kind(5), Some(SlotKind::Bits));
+        assert_eq!(moving.offset_of("mass"), None);
+
+        let fixed = StaticParticle::layout();
+        assert_eq!(size_of::<StaticParticle>(), 32);
+        assert_eq!(StaticParticle::get_f64_size(), 4);
+        assert_dense(&fixed);
+        assert_eq!(names(&fixed), ["pos.x", "pos.y", "rot", "binding_cfg_id"]);
+        assert_eq!(fixed.kind(3), Some(SlotKind::Bits));
+
+        let bonds = BondSegment::layout();
+        assert_eq!(size_of::<BondSegment>(), 48);
+        assert_eq!(BondSegment::get_f64_size(), 6);
+        assert_dense(&bonds);
+        assert_eq!(
+            names(&bonds),
+            ["from.x", "from.y", "to.x", "to.y", "port", "generation"]
+        );
+        assert!((0..bonds.fields()).all(|field| bonds.kind(field) == Some(SlotKind::Float)));
+
+        let particles = [
+            StaticParticle {
+                pos: Vector::new(1., 2.),
+                rot: 30.,
+                binding_cfg_id: 0,
+            },
+            StaticParticle {
+                pos: Vector::new(-4., 5.),
+                rot: 60.,
+                binding_cfg_id: 0,
+            },
+        ];
+        assert_eq!(as_slots(&particles), [1., 2., 30., 0., -4., 5., 60., 0.]);
+    }
+}
@@ -15,11 +15,31 @@
 */
 
 use wasm_bindgen::prelude::*;
+mod alignment;
+mod analysis;
+mod boundary;
+mod branches;
+mod components;
 mod container;
+mod delaunay;
 mod extfn;
 mod field;
+mod geometry;
+mod hull;
+mod layout;
+mod lifetime;
+mod mask;
 mod particle;
+mod profile;
+mod repulsion;
+mod rng;
+mod seeds;
+mod snowflake;
+mod species;
+mod symmetry;
+mod telemetry;
 mod vector;
+mod walk;
 
 #[cfg(not(test))]
 #[wasm_bindgen(start)]
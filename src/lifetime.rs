@@ -0,0 +1,79 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use wasm_bindgen::prelude::*;
+
+/// What happens to a moving particle that outlived its lifetime
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum ExpiryAction {
+    /// remove the particle
+    Remove,
+    /// replace it with a new particle of the same species in the spawn region
+    Respawn,
+}
+
+/// How long moving particles wandered before attaching
+#[wasm_bindgen]
+#[derive(Copy, Clone, Default)]
+pub struct WanderStatistics {
+    /// number of moving particles that attached
+    pub attached: u32,
+    /// number of moving particles removed or respawned after their lifetime
+    pub expired: u32,
+    /// longest wander time before attaching
+    pub max_time: f64,
+    // sum of wander times before attaching
+    total_time: f64,
+}
+
+#[wasm_bindgen]
+impl WanderStatistics {
+    /// average wander time before attaching
+    pub fn mean_time(&self) -> f64 {
+        if self.attached > 0 {
+            self.total_time / self.attached as f64
+        } else {
+            0.
+        }
+    }
+}
+
+impl WanderStatistics {
+    pub fn record_attachment(&mut self, wander_time: f64) {
+        self.attached += 1;
+        self.total_time += wander_time;
+        self.max_time = self.max_time.max(wander_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn wander_statistics() {
+        let mut stats = WanderStatistics::default();
+        assert_eq!(stats.mean_time(), 0.);
+        stats.record_attachment(2.);
+        stats.record_attachment(6.);
+        stats.record_attachment(1.);
+        assert_eq!(stats.attached, 3);
+        assert_eq!(stats.mean_time(), 3.);
+        assert_eq!(stats.max_time, 6.);
+    }
+}
@@ -0,0 +1,129 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::vector::Vector;
+
+/// Greyscale bitmap stretched over the field bounding rectangle.
+/// Rows go top to bottom, i.e. first row is at -dimensions.y
+pub struct Mask {
+    width: usize,
+    height: usize,
+    // one byte per pixel, row-major
+    values: Vec<u8>,
+    // field dimensions, from -dim to +dim
+    dimensions: Vector,
+}
+
+impl Mask {
+    /// Create a mask from raw pixel data, if there is enough of it
+    pub fn new(data: &[u8], width: usize, height: usize, dimensions: &Vector) -> Option<Self> {
+        let size = width.checked_mul(height)?;
+        if size == 0 || data.len() < size {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            values: data[..size].to_vec(),
+            dimensions: *dimensions,
+        })
+    }
+
+    /// binary mask: pixels at or above threshold are set to 255, the rest to 0
+    pub fn threshold(mut self, threshold: u8) -> Self {
+        for value in self.values.iter_mut() {
+            *value = if *value >= threshold { 255 } else { 0 };
+        }
+        self
+    }
+
+    /// pixel value under a position, 0 outside of the field
+    pub fn value(&self, pos: &Vector) -> u8 {
+        let u = (pos.x + self.dimensions.x) / (2. * self.dimensions.x);
+        let v = (pos.y + self.dimensions.y) / (2. * self.dimensions.y);
+        if !(0. ..=1.).contains(&u) || !(0. ..=1.).contains(&v) {
+            return 0;
+        }
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.values[x + y * self.width]
+    }
+
+    /// check if a pixel under a position is non-zero
+    pub fn is_set(&self, pos: &Vector) -> bool {
+        self.value(pos) > 0
+    }
+
+    /// pixel value under a position scaled to [0, 1]
+    pub fn weight(&self, pos: &Vector) -> f64 {
+        self.value(pos) as f64 / 255.
+    }
+
+    /// positions of a square grid over the field that fall on non-zero pixels
+    pub fn grid_positions(&self, spacing: f64) -> Vec<Vector> {
+        if !(spacing > 0. && spacing.is_finite()) {
+            return Vec::new();
+        }
+        let columns = (2. * self.dimensions.x / spacing) as usize;
+        let rows = (2. * self.dimensions.y / spacing) as usize;
+        let mut positions = Vec::new();
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let pos = Vector::new(
+                    -self.dimensions.x + column as f64 * spacing,
+                    -self.dimensions.y + row as f64 * spacing,
+                );
+                if self.is_set(&pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn field_mapping() {
+        let dimensions = Vector::new(20., 10.);
+        assert!(Mask::new(&[0; 7], 4, 2, &dimensions).is_none());
+        assert!(Mask::new(&[0; 8], usize::MAX, 2, &dimensions).is_none());
+
+        // 4x2 pixels, each covering 10x10 units of the field
+        let data = [0, 10, 200, 255, 0, 0, 100, 0];
+        let mask = Mask::new(&data, 4, 2, &dimensions).unwrap();
+        assert_eq!(mask.value(&Vector::new(-15., -5.)), 0);
+        assert_eq!(mask.value(&Vector::new(-5., -5.)), 10);
+        assert_eq!(mask.value(&Vector::new(15., -5.)), 255);
+        assert_eq!(mask.value(&Vector::new(5., 5.)), 100);
+        assert_eq!(mask.value(&Vector::new(20., 10.)), 0);
+        assert_eq!(mask.value(&Vector::new(25., -5.)), 0);
+        assert!((mask.weight(&Vector::new(15., -1.)) - 1.).abs() < 1e-12);
+
+        let binary = mask.threshold(100);
+        assert!(!binary.is_set(&Vector::new(-5., -5.)));
+        assert!(binary.is_set(&Vector::new(5., 5.)));
+        // 9x5 grid nodes: 5 columns x 2 rows on the top right pixels,
+        // 2 columns x 3 rows on the bottom one (far edges map to the last pixel)
+        assert_eq!(binary.grid_positions(5.).len(), 16);
+        assert!(binary.grid_positions(0.).is_empty());
+        assert!(binary.grid_positions(f64::NAN).is_empty());
+    }
+}
@@ -15,25 +15,59 @@
 */
 
 use crate::extfn;
+use crate::layout::{slot, BufferLayout, BufferRecord, SlotKind};
 use crate::vector::Vector;
 use std::f64::consts::PI;
 use wasm_bindgen::prelude::*;
 
 // valid ID configuration bits
 const BIND_CFG_ID_MASK: u64 = 0xff;
+// moving particle species bits in flags
+const SPECIES_MASK: u64 = 0xff;
+// moving particle busy ports bits in flags
+const PORTS_SHIFT: u64 = 16;
+const PORTS_MASK: u64 = 0xff << PORTS_SHIFT;
+// moving particle cluster id bits in flags
+const CLUSTER_SHIFT: u64 = 32;
+const CLUSTER_MASK: u64 = (u32::MAX as u64) << CLUSTER_SHIFT;
 
 pub trait Positionable {
     fn position(&self) -> Vector;
 }
 
+/// Moving particle state bits, stored in `MovingParticle::flags`
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+pub enum ParticleFlag {
+    /// followed by the user
+    Tracked = 0x100,
+    /// does not move (but can still attach)
+    Frozen = 0x200,
+    /// rendered with a highlight
+    Highlighted = 0x400,
+    /// placed by hand rather than spawned
+    Seeded = 0x800,
+}
+
 /// A moving particle on the field
+///
+/// Renderer-facing buffer layout (`Field::moving_particles_view`, `MovingParticle::layout`),
+/// 9 x 8 bytes per particle:
+/// [pos.x, pos.y, vel.x, vel.y, since, flags, speed, rot, spin];
+/// all are f64 except flags, which are u64 bits and must be read as integers:
+/// bits 0-7 species, bits 8-15 `ParticleFlag`s, bits 16-23 ports bonded to
+/// other moving particles, bits 32-63 cluster id (0 for a free particle)
 #[wasm_bindgen]
 #[derive(Copy, Clone, PartialEq)]
+#[repr(C)]
 pub struct MovingParticle {
     pub pos: Vector,
     pub vel: Vector,
     pub since: f64, // engine time when this particle appeared
-    pub flags: u64,
+    pub flags: u64, // species and state bits, see above
+    pub speed: f64, // displacement multiplier, drawn at spawn
+    pub rot: f64,   // rotation, degrees
+    pub spin: f64,  // angular velocity, degrees per time unit
 }
 
 impl Default for MovingParticle {
@@ -43,13 +77,66 @@ impl Default for MovingParticle {
             vel: Vector { x: 0., y: 0. },
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         }
     }
 }
 #[wasm_bindgen]
 impl MovingParticle {
     pub fn get_f64_size() -> usize {
-        6
+        Self::layout().stride
+    }
+    /// names and slots of values in the moving particle buffer
+    pub fn layout() -> BufferLayout {
+        <Self as BufferRecord>::layout()
+    }
+
+    /// species index, stored in the lowest flag bits
+    pub fn species(&self) -> usize {
+        (self.flags & SPECIES_MASK) as usize
+    }
+    pub fn set_species(&mut self, species: u8) {
+        self.flags = (self.flags & !SPECIES_MASK) | species as u64;
+    }
+
+    pub fn has_flag(&self, flag: ParticleFlag) -> bool {
+        self.flags & flag as u64 != 0
+    }
+    pub fn set_flag(&mut self, flag: ParticleFlag, on: bool) {
+        if on {
+            self.flags |= flag as u64;
+        } else {
+            self.flags &= !(flag as u64);
+        }
+    }
+
+    /// id of a drifting cluster this particle belongs to, 0 if none
+    pub fn cluster(&self) -> u32 {
+        (self.flags >> CLUSTER_SHIFT) as u32
+    }
+    pub fn set_cluster(&mut self, cluster: u32) {
+        self.flags = (self.flags & !CLUSTER_MASK) | ((cluster as u64) << CLUSTER_SHIFT);
+    }
+}
+
+impl MovingParticle {
+    /// ports bonded to other moving particles (bitmask)
+    pub fn busy_ports(&self) -> u8 {
+        ((self.flags & PORTS_MASK) >> PORTS_SHIFT) as u8
+    }
+    pub fn set_busy_ports(&mut self, ports: u8) {
+        self.flags = (self.flags & !PORTS_MASK) | ((ports as u64) << PORTS_SHIFT);
+    }
+
+    /// static copy of this particle, to bind other particles to it
+    pub fn as_static(&self) -> StaticParticle {
+        StaticParticle {
+            pos: self.pos,
+            rot: self.rot,
+            binding_cfg_id: (self.busy_ports() as u64) << 32,
+        }
     }
 }
 
@@ -58,8 +145,13 @@ impl Positionable for MovingParticle {
 }
 
 /// A static particle on the field
+///
+/// Renderer-facing buffer layout (`Field::static_particles_view`, `StaticParticle::layout`),
+/// 4 x 8 bytes per particle: [pos.x, pos.y, rot, binding_cfg_id];
+/// binding_cfg_id is u64 bits and must be read as an integer
 #[wasm_bindgen]
 #[derive(Copy, Clone, PartialEq)]
+#[repr(C)]
 pub struct StaticParticle {
     /// Static particle position
     pub pos: Vector,
@@ -81,12 +173,98 @@ impl Default for StaticParticle {
 #[wasm_bindgen]
 impl StaticParticle {
     pub fn get_f64_size() -> usize {
-        4
+        Self::layout().stride
+    }
+    /// names and slots of values in the static particle buffer
+    pub fn layout() -> BufferLayout {
+        <Self as BufferRecord>::layout()
     }
 }
 impl Positionable for StaticParticle {
     fn position(&self) -> Vector { self.pos }
 }
+
+/// A bond between two static particles, to draw crystals as skeletons
+/// Renderer-facing buffer layout (`Field::bond_segments_view`, `BondSegment::layout`),
+/// 6 x 8 bytes per bond:
+/// [from.x, from.y, to.x, to.y, port, generation], all f64
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct BondSegment {
+    /// Position of the particle bound to
+    pub from: Vector,
+    /// Position of the bound particle (across the edge of a periodic field
+    /// this is outside the field, so that bonds stay short)
+    pub to: Vector,
+    /// Port of the particle bound to, -1 if the bond does not face a port
+    pub port: f64,
+    /// Number of bonds between the bound particle and its seed
+    pub generation: f64,
+}
+#[wasm_bindgen]
+impl BondSegment {
+    pub fn get_f64_size() -> usize {
+        Self::layout().stride
+    }
+    /// names and slots of values in the bond segment buffer
+    pub fn layout() -> BufferLayout {
+        <Self as BufferRecord>::layout()
+    }
+}
+
+unsafe impl BufferRecord for MovingParticle {
+    fn layout() -> BufferLayout {
+        let p = Self::default();
+        BufferLayout::new::<Self>(&[
+            ("pos.x", slot(&p, &p.pos.x), SlotKind::Float),
+            ("pos.y", slot(&p, &p.pos.y), SlotKind::Float),
+            ("vel.x", slot(&p, &p.vel.x), SlotKind::Float),
+            ("vel.y", slot(&p, &p.vel.y), SlotKind::Float),
+            ("since", slot(&p, &p.since), SlotKind::Float),
+            ("flags", slot(&p, &p.flags), SlotKind::Bits),
+            ("speed", slot(&p, &p.speed), SlotKind::Float),
+            ("rot", slot(&p, &p.rot), SlotKind::Float),
+            ("spin", slot(&p, &p.spin), SlotKind::Float),
+        ])
+    }
+}
+
+unsafe impl BufferRecord for StaticParticle {
+    fn layout() -> BufferLayout {
+        let p = Self::default();
+        BufferLayout::new::<Self>(&[
+            ("pos.x", slot(&p, &p.pos.x), SlotKind::Float),
+            ("pos.y", slot(&p, &p.pos.y), SlotKind::Float),
+            ("rot", slot(&p, &p.rot), SlotKind::Float),
+            (
+                "binding_cfg_id",
+                slot(&p, &p.binding_cfg_id),
+                SlotKind::Bits,
+            ),
+        ])
+    }
+}
+
+unsafe impl BufferRecord for BondSegment {
+    fn layout() -> BufferLayout {
+        let origin = Vector::new(0., 0.);
+        let b = Self {
+            from: origin,
+            to: origin,
+            port: -1.,
+            generation: 0.,
+        };
+        BufferLayout::new::<Self>(&[
+            ("from.x", slot(&b, &b.from.x), SlotKind::Float),
+            ("from.y", slot(&b, &b.from.y), SlotKind::Float),
+            ("to.x", slot(&b, &b.to.x), SlotKind::Float),
+            ("to.y", slot(&b, &b.to.y), SlotKind::Float),
+            ("port", slot(&b, &b.port), SlotKind::Float),
+            ("generation", slot(&b, &b.generation), SlotKind::Float),
+        ])
+    }
+}
 impl StaticParticle {
     fn bind_config_and_port(cfg_id: u64, port: u8) -> u64 {
         (cfg_id & BIND_CFG_ID_MASK) | (1u64 << (32 + port))
@@ -97,16 +275,24 @@ impl StaticParticle {
     fn count_busy_ports(&self) -> u8 {
         (self.binding_cfg_id & (255u64 << 32)).count_ones() as u8
     }
-    fn set_port_busy(&mut self, port: u8) {
+    pub fn set_port_busy(&mut self, port: u8) {
         self.binding_cfg_id |= 1u64 << (32 + port);
     }
+    /// busy ports (bitmask)
+    pub fn busy_ports(&self) -> u8 {
+        (self.binding_cfg_id >> 32) as u8
+    }
 }
 
 /// How to align a moving particle during attachment
+#[wasm_bindgen]
 #[derive(Copy, Clone, PartialEq)]
-enum AttachmentAlignment {
+pub enum AttachmentAlignment {
+    /// take the rotation of the static particle (perfect crystal)
     Zero,
+    /// turn the particle so that its port faces the static one (irregular crystal)
     Port,
+    /// keep the particle rotation and position (non-crystalline growth)
     Free,
 }
 
@@ -173,6 +359,9 @@ impl BindingConfiguration {
     pub fn set_radius(&mut self, radius: f64) {
         self.radius = radius;
     }
+    pub fn set_alignment(&mut self, align: AttachmentAlignment) {
+        self.align = align;
+    }
     pub fn radius(&self) -> f64 {
         self.radius
     }
@@ -203,6 +392,18 @@ impl BindingConfiguration {
     pub fn close_enough_to_bind(&self, pos1: &Vector, pos2: &Vector) -> bool {
         Vector::distance_squared(pos1, pos2) <= (self.radius * self.radius * 1.0000001)
     }
+
+    /// port of a static particle facing a position
+    pub fn port_towards(&self, sp: &StaticParticle, pos: &Vector) -> Option<u8> {
+        let diff = Vector::diff(pos, &sp.pos);
+        let angle = extfn::atan2(diff.y, diff.x) / PI * 180.;
+        self.angle_to_port(angle - sp.rot).map(|port| port as u8)
+    }
+
+    /// check if a static particle can accept one more bond
+    pub fn has_free_port(&self, sp: &StaticParticle) -> bool {
+        sp.count_busy_ports() < self.max_binds && self.attachment_site_mask & !sp.busy_ports() != 0
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -248,8 +449,8 @@ impl BindingResult {
                 // - zero removes variance
                 match sp_bind_cfg.align {
                     AttachmentAlignment::Free => {
-                        // use movement direction as rotation
-                        let rot = mp.vel.y.atan2(mp.vel.x) / PI * 180.;
+                        // keep moving particle rotation
+                        let rot = mp.rot;
                         mp_bind_cfg
                             .angle_to_port(180. + angle_to_mp - rot)
                             .filter(|&port_at_mp| mp_bind_cfg.is_port_attachable(port_at_mp))
@@ -262,7 +463,7 @@ impl BindingResult {
                     AttachmentAlignment::Port => {
                         // align port centers of moving and static particles
                         // (particle is expected to be pulled up to the rendezvous point)
-                        let rot = mp.vel.y.atan2(mp.vel.x) / PI * 180.;
+                        let rot = mp.rot;
                         mp_bind_cfg
                             .angle_to_port(180. + angle_to_mp - rot)
                             .filter(|&port_at_mp| mp_bind_cfg.is_port_attachable(port_at_mp))
@@ -288,6 +489,19 @@ impl BindingResult {
             })
     }
 
+    /// Port of the moving particle taking part in the bond
+    pub fn site_at_moving(&self) -> u8 {
+        self.site_at_moving
+    }
+
+    /// Direction of the bond from static particle to the new one, degrees
+    pub fn port_direction(&self, sp: &StaticParticle, sp_bind_cfg: &BindingConfiguration) -> f64 {
+        sp_bind_cfg
+            .port_to_angle(self.site_at_static as usize)
+            .map(|x| x + sp.rot)
+            .unwrap_or(0.)
+    }
+
     /// Apply a BindingResult to a pair of moving/static particles
     /// Return a static particle that is created as a result of binidng
     pub fn apply_binding(
@@ -304,10 +518,7 @@ impl BindingResult {
         sp.set_port_busy(self.site_at_static);
 
         // get angle at binding site
-        let angle = sp_bind_cfg
-            .port_to_angle(self.site_at_static as usize)
-            .map(|x| x + sp.rot)
-            .unwrap_or(0.);
+        let angle = self.port_direction(sp, sp_bind_cfg);
         let pos = match sp_bind_cfg.align {
             AttachmentAlignment::Free => mp.pos,
             _ => {
@@ -366,8 +577,7 @@ mod tests {
                 "p" is shifted down to center at port
 
             Receiving port and moving port are determined according to relative
-            position and orientation. Moving particle rotation is obtained from
-            velocity vector.
+            position and orientation of both particles.
             Moving particle is snapped to align to the center of receiving port
             and rotated to center at its own port
 
@@ -411,15 +621,14 @@ mod tests {
             vel: Vector { x: 1., y: 0. },
             since: 0.,
             flags: 0,
+            speed: 1.,
+            rot: 0.,
+            spin: 0.,
         };
         // sp and mp rotation
         for ro in 0..4 {
             sp.rot = 90. * (ro as f64);
-            let angle = ro as f64 * PI / 2.;
-            mp.vel = Vector {
-                x: angle.cos(),
-                y: angle.sin(),
-            };
+            mp.rot = 90. * (ro as f64);
             let bind_result = BindingResult::get_binding(&mp, &sp, &bind_port, &bind_port);
             assert_eq!(
                 bind_result,
@@ -455,6 +664,28 @@ mod tests {
         }
     }
 
+    #[wasm_bindgen_test]
+    fn particle_flags() {
+        let mut mp = MovingParticle::default();
+        mp.set_species(3);
+        mp.set_flag(ParticleFlag::Tracked, true);
+        mp.set_flag(ParticleFlag::Seeded, true);
+        assert_eq!(mp.species(), 3);
+        assert!(mp.has_flag(ParticleFlag::Tracked) && mp.has_flag(ParticleFlag::Seeded));
+        assert!(!mp.has_flag(ParticleFlag::Frozen));
+
+        mp.set_flag(ParticleFlag::Tracked, false);
+        mp.set_species(1);
+        assert_eq!(mp.flags, 0x801);
+
+        mp.set_cluster(0xdead_beef);
+        mp.set_busy_ports(0b101);
+        assert_eq!(mp.cluster(), 0xdead_beef);
+        assert_eq!(mp.as_static().busy_ports(), 0b101);
+        mp.set_cluster(7);
+        assert_eq!(mp.flags, 0x7_0005_0801);
+    }
+
     #[wasm_bindgen_test]
     fn port_angle() {
         let cfg = BindingConfiguration::make_square();
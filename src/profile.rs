@@ -0,0 +1,161 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+use crate::extfn;
+use crate::vector::Vector;
+
+/// Highest angular harmonic in a density profile
+pub const MAX_HARMONIC: usize = 12;
+
+/// Particle density around a centre, by angle and by distance
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct DensityProfile {
+    // particles per angular bin, counterclockwise from +x
+    angular: Vec<f64>,
+    // particles per unit area in rings of equal width, from the centre outwards
+    radial: Vec<f64>,
+    // strength of n-fold angular harmonics, 0 to MAX_HARMONIC
+    harmonics: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl DensityProfile {
+    pub fn angular(&self) -> Vec<f64> {
+        self.angular.clone()
+    }
+    pub fn radial(&self) -> Vec<f64> {
+        self.radial.clone()
+    }
+    pub fn harmonics(&self) -> Vec<f64> {
+        self.harmonics.clone()
+    }
+    /// strength of n-fold symmetry: 1 if every particle lies on n equally spaced
+    /// directions, about 0 for a disordered crystal
+    pub fn harmonic(&self, order: usize) -> f64 {
+        self.harmonics.get(order).copied().unwrap_or(0.)
+    }
+}
+
+impl DensityProfile {
+    pub fn new(
+        points: &[Vector],
+        center: &Vector,
+        angular_bins: usize,
+        radial_bins: usize,
+        ring_width: f64,
+    ) -> Self {
+        let angles = points
+            .iter()
+            .filter(|p| *p != center)
+            .map(|p| extfn::atan2(p.y - center.y, p.x - center.x))
+            .collect::<Vec<_>>();
+        Self {
+            angular: angular_profile(&angles, angular_bins),
+            radial: radial_profile(points, center, radial_bins, ring_width),
+            harmonics: (0..=MAX_HARMONIC)
+                .map(|order| angular_harmonic(&angles, order))
+                .collect(),
+        }
+    }
+}
+
+/// number of angles (radians) in equal angular bins, starting from 0
+pub fn angular_profile(angles: &[f64], bins: usize) -> Vec<f64> {
+    let mut profile = vec![0.; bins];
+    if bins > 0 {
+        for angle in angles {
+            let bin = (angle.rem_euclid(2. * PI) / (2. * PI) * bins as f64) as usize;
+            profile[bin.min(bins - 1)] += 1.;
+        }
+    }
+    profile
+}
+
+/// number of points per unit area in rings around the centre
+pub fn radial_profile(
+    points: &[Vector],
+    center: &Vector,
+    bins: usize,
+    ring_width: f64,
+) -> Vec<f64> {
+    let mut profile = vec![0.; bins];
+    if ring_width > 0. {
+        for p in points {
+            let bin = (Vector::length(&Vector::diff(p, center)) / ring_width) as usize;
+            if let Some(count) = profile.get_mut(bin) {
+                *count += 1.;
+            }
+        }
+    }
+    for (bin, density) in profile.iter_mut().enumerate() {
+        let (inner, outer) = (bin as f64 * ring_width, (bin + 1) as f64 * ring_width);
+        if outer > 0. {
+            *density /= PI * (outer * outer - inner * inner);
+        }
+    }
+    profile
+}
+
+/// magnitude of the n-th Fourier coefficient of the angular distribution,
+/// from 0 to 1
+pub fn angular_harmonic(angles: &[f64], order: usize) -> f64 {
+    if angles.is_empty() {
+        return 0.;
+    }
+    let (cos, sin) = angles.iter().fold((0., 0.), |(cos, sin), angle| {
+        let (s, c) = (angle * order as f64).sin_cos();
+        (cos + c, sin + s)
+    });
+    (cos * cos + sin * sin).sqrt() / angles.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn six_fold_profile() {
+        let center = Vector::new(10., -5.);
+        // six straight arms
+        let mut points = vec![center];
+        for arm in 0..6 {
+            let (sin, cos) = ((15. + 60. * arm as f64) * PI / 180.).sin_cos();
+            for r in 1..=10 {
+                points.push(center + Vector::new(cos, sin) * r as f64);
+            }
+        }
+        let profile = DensityProfile::new(&points, &center, 12, 2, 5.5);
+        assert_eq!(
+            profile.angular(),
+            vec![10., 0., 10., 0., 10., 0., 10., 0., 10., 0., 10., 0.]
+        );
+        let radial = profile.radial();
+        assert!((radial[0] - 31. / (30.25 * PI)).abs() < 1e-9, "{:?}", radial);
+        assert!((radial[1] - 30. / (90.75 * PI)).abs() < 1e-9, "{:?}", radial);
+
+        assert!((profile.harmonic(0) - 1.).abs() < 1e-9);
+        assert!((profile.harmonic(6) - 1.).abs() < 1e-9);
+        assert!((profile.harmonic(12) - 1.).abs() < 1e-9);
+        assert!(profile.harmonic(4) < 1e-9);
+        assert!(profile.harmonic(1) < 1e-9);
+        assert_eq!(profile.harmonic(13), 0.);
+    }
+}
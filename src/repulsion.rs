@@ -0,0 +1,83 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::vector::Vector;
+
+/// Interaction between moving particles
+#[derive(Copy, Clone, PartialEq)]
+pub enum Repulsion {
+    /// particles pass through each other
+    None,
+    /// linear push apart, from strength at contact down to zero at range
+    Soft { range: f64, strength: f64 },
+    /// particle centres can not come closer than radius
+    HardCore { radius: f64 },
+}
+
+impl Repulsion {
+    /// force on a particle from a neighbour, given separation from neighbour to particle
+    /// (hard core exclusion does not produce forces, see `blocks`)
+    pub fn force(&self, separation: &Vector) -> Vector {
+        match *self {
+            Repulsion::Soft { range, strength } => {
+                let distance = Vector::length(separation);
+                if distance >= range || distance == 0. {
+                    Vector::new(0., 0.)
+                } else {
+                    *separation * (strength * (1. - distance / range) / distance)
+                }
+            }
+            _ => Vector::new(0., 0.),
+        }
+    }
+
+    /// check if a move changing distance to a neighbour is not allowed;
+    /// overlapping particles may still move apart
+    pub fn blocks(&self, old_distance: f64, new_distance: f64) -> bool {
+        match *self {
+            Repulsion::HardCore { radius } => new_distance < radius && new_distance < old_distance,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn repulsion_forces() {
+        let soft = Repulsion::Soft {
+            range: 4.,
+            strength: 2.,
+        };
+        assert!(soft.force(&Vector::new(1., 0.)) == Vector::new(1.5, 0.));
+        assert!(soft.force(&Vector::new(0., -3.)) == Vector::new(0., -0.5));
+        assert!(soft.force(&Vector::new(5., 0.)) == Vector::new(0., 0.));
+        assert!(!soft.blocks(3., 0.5));
+
+        let hard = Repulsion::HardCore { radius: 2. };
+        assert!(hard.force(&Vector::new(1., 0.)) == Vector::new(0., 0.));
+        assert!(hard.blocks(3., 1.5));
+        assert!(!hard.blocks(3., 2.5));
+        assert!(
+            !hard.blocks(1., 1.5),
+            "Overlapping particles must be able to separate"
+        );
+        assert!(Repulsion::None.force(&Vector::new(1., 0.)) == Vector::new(0., 0.));
+    }
+}
@@ -0,0 +1,190 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+// Seed pattern generators
+// Every generator returns positions only; placing them (and rejecting those
+// that cannot attach) is up to the field. Patterns are cut off after `limit`
+// positions, so that a tiny spacing or a huge size cannot exhaust memory
+
+use std::f64::consts::PI;
+
+use crate::boundary::Boundary;
+use crate::rng::Rng;
+use crate::vector::Vector;
+
+/// Random constellation of `points` seeds, each replicated `order` times
+/// around the origin, spreading out up to `radius`
+pub fn symmetric_random(rng: &mut Rng, points: u32, order: u8, radius: f64) -> Vec<Vector> {
+    let order = order.max(1);
+    let mut seeds = Vec::with_capacity(points as usize * order as usize);
+    for i in 0..points {
+        let theta = rng.random() * 2. * PI;
+        // later points are placed further from the center
+        let r = (0.2 + rng.random()) * ((i + 1) as f64 * 0.5 / points as f64) * radius;
+        for m in 0..order {
+            let angle = theta + m as f64 * 2. * PI / order as f64;
+            seeds.push(Vector::new(angle.sin() * r, angle.cos() * r));
+        }
+    }
+    seeds
+}
+
+/// Spacing that gives a finite number of seeds
+fn is_valid_spacing(spacing: f64) -> bool {
+    spacing > 0. && spacing.is_finite()
+}
+
+/// Circle of seeds about `spacing` apart
+pub fn circle(center: &Vector, radius: f64, spacing: f64, limit: usize) -> Vec<Vector> {
+    if !is_valid_spacing(spacing) || !radius.is_finite() {
+        return Vec::new();
+    }
+    let count = ((2. * PI * radius / spacing).round() as usize).max(3);
+    (0..count)
+        .map(|i| {
+            let (sin, cos) = (2. * PI * i as f64 / count as f64).sin_cos();
+            Vector::new(center.x + radius * cos, center.y + radius * sin)
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Line segment of seeds, including both ends
+pub fn line(from: &Vector, to: &Vector, spacing: f64, limit: usize) -> Vec<Vector> {
+    line_positions(from, to, spacing).take(limit).collect()
+}
+
+/// Positions of `line`, generated lazily
+fn line_positions(from: &Vector, to: &Vector, spacing: f64) -> impl Iterator<Item = Vector> {
+    let (from, diff) = (*from, Vector::diff(to, from));
+    let length = Vector::length(&diff);
+    let count = if !is_valid_spacing(spacing) || !length.is_finite() {
+        0
+    } else if length == 0. {
+        1
+    } else {
+        ((length / spacing).round() as usize)
+            .max(1)
+            .saturating_add(1)
+    };
+    let steps = (count.max(2) - 1) as f64;
+    (0..count).map(move |i| from + diff * (i as f64 / steps))
+}
+
+/// Outline of a regular polygon; `rotation` is in degrees
+pub fn polygon(
+    center: &Vector,
+    radius: f64,
+    sides: u32,
+    rotation: f64,
+    spacing: f64,
+    limit: usize,
+) -> Vec<Vector> {
+    let sides = sides.max(3);
+    let vertex = |i: u32| {
+        let (sin, cos) = (rotation * PI / 180. + 2. * PI * i as f64 / sides as f64).sin_cos();
+        Vector::new(center.x + radius * cos, center.y + radius * sin)
+    };
+    (0..sides)
+        .flat_map(|i| {
+            let mut edge = line(&vertex(i), &vertex(i + 1), spacing, limit.saturating_add(1));
+            // next edge starts at this edge's end
+            edge.pop();
+            edge
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Archimedean spiral, `pitch` is the distance between successive turns
+pub fn spiral(center: &Vector, turns: f64, pitch: f64, spacing: f64, limit: usize) -> Vec<Vector> {
+    if !is_valid_spacing(spacing) || !turns.is_finite() || !pitch.is_finite() {
+        return Vec::new();
+    }
+    let b = pitch / (2. * PI);
+    let max_theta = turns * 2. * PI;
+    let mut seeds = Vec::new();
+    let mut theta = 0.;
+    while theta <= max_theta && seeds.len() < limit {
+        let r = b * theta;
+        let (sin, cos) = theta.sin_cos();
+        seeds.push(Vector::new(center.x + r * cos, center.y + r * sin));
+        // arc length element is sqrt(r^2 + b^2) d(theta)
+        theta += spacing / (r * r + b * b).sqrt();
+    }
+    seeds
+}
+
+/// Horizontal substrate line `height` above the bottom of the field,
+/// clipped to the field boundary
+pub fn substrate(boundary: &Boundary, height: f64, spacing: f64, limit: usize) -> Vec<Vector> {
+    let half = boundary.half_extents();
+    let y = half.y - height;
+    line_positions(&Vector::new(-half.x, y), &Vector::new(half.x, y), spacing)
+        .filter(|v| boundary.contains(v))
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn pattern_sizes() {
+        let origin = Vector::new(0., 0.);
+        assert_eq!(line(&origin, &Vector::new(10., 0.), 5., 100).len(), 3);
+        assert_eq!(line(&origin, &origin, 5., 100).len(), 1);
+        assert_eq!(polygon(&origin, 10., 4, 45., 100., 100).len(), 4);
+        assert_eq!(circle(&origin, 10., 2. * PI, 100).len(), 10);
+
+        let mut rng = Rng::new(42);
+        let seeds = symmetric_random(&mut rng, 3, 6, 100.);
+        assert_eq!(seeds.len(), 18);
+        let mut other = Rng::new(42);
+        assert!(
+            seeds == symmetric_random(&mut other, 3, 6, 100.),
+            "Not reproducible"
+        );
+
+        let floor = substrate(&Boundary::Ellipse(Vector::new(100., 100.)), 10., 5., 100);
+        assert!(!floor.is_empty());
+        assert!(floor
+            .iter()
+            .all(|v| (v.y - 90.).abs() < 1e-9 && v.x.abs() < 44.));
+    }
+
+    #[wasm_bindgen_test]
+    fn invalid_spacing() {
+        let origin = Vector::new(0., 0.);
+        let end = Vector::new(10., 0.);
+        for &spacing in &[0., -1., f64::NAN, f64::INFINITY] {
+            assert!(circle(&origin, 10., spacing, 100).is_empty());
+            assert!(line(&origin, &end, spacing, 100).is_empty());
+            assert!(polygon(&origin, 10., 4, 0., spacing, 100).is_empty());
+            assert!(spiral(&origin, 3., 5., spacing, 100).is_empty());
+        }
+        assert!(circle(&origin, f64::INFINITY, 1., 100).is_empty());
+        assert!(spiral(&origin, f64::INFINITY, 5., 1., 100).is_empty());
+
+        // tiny spacing and huge sizes are cut off at the limit
+        assert_eq!(circle(&origin, 10., 1e-12, 100).len(), 100);
+        assert_eq!(line(&origin, &end, 1e-12, 100).len(), 100);
+        assert_eq!(polygon(&origin, 1e15, 4, 0., 1., 100).len(), 100);
+        assert_eq!(spiral(&origin, 1e15, 5., 1., 100).len(), 100);
+    }
+}
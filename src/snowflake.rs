@@ -0,0 +1,273 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use wasm_bindgen::prelude::*;
+
+use crate::particle::StaticParticle;
+use crate::vector::Vector;
+
+/// Neighbour offsets in axial coordinates, ordered counter-clockwise
+/// starting at 0 degrees; index matches the hexa binding port
+const HEX_NEIGHBOURS: [(isize, isize); 6] = [(1, 0), (0, 1), (-1, 1), (-1, 0), (0, -1), (1, -1)];
+
+/// Reiter snowflake automaton on a hexagonal lattice
+///
+/// Every cell holds an amount of water `s`; cells with `s >= 1` are frozen.
+/// A cell is receptive if it is frozen or has a frozen neighbour. On every step
+/// receptive cells gain `gamma` of vapour and keep their water, while
+/// the rest of the water diffuses between non-receptive cells at rate `alpha`.
+/// Cells on the lattice rim are kept at background level `beta`.
+///
+/// Cells are stored in axial coordinates (q, r) in a square array;
+/// cells further than `radius` hex steps from the center are not simulated.
+#[wasm_bindgen]
+pub struct Snowflake {
+    // lattice radius in cells
+    radius: usize,
+    // water level per cell
+    cells: Vec<f64>,
+    // diffusing part of water level, scratch buffer
+    diffusing: Vec<f64>,
+    // diffusion rate
+    alpha: f64,
+    // background vapour level
+    beta: f64,
+    // vapour addition at receptive cells
+    gamma: f64,
+    // distance between neighbouring cells in field units
+    spacing: f64,
+    // exported frozen cells
+    particles: Vec<StaticParticle>,
+}
+
+#[wasm_bindgen]
+impl Snowflake {
+    #[wasm_bindgen(constructor)]
+    pub fn new(radius: usize, spacing: f64, alpha: f64, beta: f64, gamma: f64) -> Snowflake {
+        let side = 2 * radius + 1;
+        let mut flake = Snowflake {
+            radius,
+            cells: vec![beta; side * side],
+            diffusing: vec![0.; side * side],
+            alpha,
+            beta,
+            gamma,
+            spacing,
+            particles: Vec::new(),
+        };
+        flake.reset();
+        flake
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+    pub fn set_alpha(&mut self, alpha: f64) {
+        self.alpha = alpha;
+    }
+    pub fn beta(&self) -> f64 {
+        self.beta
+    }
+    pub fn set_beta(&mut self, beta: f64) {
+        self.beta = beta;
+    }
+    pub fn gamma(&self) -> f64 {
+        self.gamma
+    }
+    pub fn set_gamma(&mut self, gamma: f64) {
+        self.gamma = gamma;
+    }
+
+    /// restart growth from a single frozen cell in the center
+    pub fn reset(&mut self) {
+        let beta = self.beta;
+        self.cells.iter_mut().for_each(|s| *s = beta);
+        let center = self.linear(0, 0);
+        self.cells[center] = 1.;
+        self.particles.clear();
+    }
+
+    /// advance the automaton by one step;
+    /// return false once the crystal has reached the lattice rim
+    pub fn step(&mut self) -> bool {
+        let radius = self.radius as isize;
+
+        // split water into receptive (frozen in place) and diffusing parts
+        let mut receptive = vec![false; self.cells.len()];
+        for r in -radius..=radius {
+            for q in -radius..=radius {
+                if let Some(index) = self.index(q, r) {
+                    receptive[index] = self.is_frozen(q, r)
+                        || HEX_NEIGHBOURS
+                            .iter()
+                            .any(|&(dq, dr)| self.is_frozen(q + dq, r + dr));
+                    self.diffusing[index] = if receptive[index] {
+                        0.
+                    } else {
+                        self.cells[index]
+                    };
+                }
+            }
+        }
+
+        let mut reached_rim = false;
+        for r in -radius..=radius {
+            for q in -radius..=radius {
+                if let Some(index) = self.index(q, r) {
+                    if Self::hex_distance(q, r) == radius {
+                        // rim is an infinite vapour source
+                        self.cells[index] = self.beta;
+                        continue;
+                    }
+                    let neighbours_avg = HEX_NEIGHBOURS
+                        .iter()
+                        .map(|&(dq, dr)| self.diffusing_at(q + dq, r + dr))
+                        .sum::<f64>()
+                        / 6.;
+                    let u = self.diffusing[index];
+                    let diffused = u + self.alpha / 2. * (neighbours_avg - u);
+                    let fixed = if receptive[index] {
+                        self.cells[index] + self.gamma
+                    } else {
+                        0.
+                    };
+                    self.cells[index] = diffused + fixed;
+                    if self.cells[index] >= 1. && Self::hex_distance(q, r) >= radius - 1 {
+                        reached_rim = true;
+                    }
+                }
+            }
+        }
+        !reached_rim
+    }
+
+    /// number of frozen cells
+    pub fn frozen_count(&self) -> usize {
+        self.cells.iter().filter(|&&s| s >= 1.).count()
+    }
+
+    /// convert frozen cells to static particles, return their count
+    ///
+    /// Particles are rotated so that hexa binding ports point
+    /// at lattice neighbours, and ports towards frozen neighbours are marked busy.
+    pub fn export_static_particles(&mut self) -> usize {
+        let radius = self.radius as isize;
+        let mut particles = Vec::with_capacity(self.frozen_count());
+        for r in -radius..=radius {
+            for q in -radius..=radius {
+                if !self.is_frozen(q, r) {
+                    continue;
+                }
+                let mut particle = StaticParticle {
+                    pos: self.position(q, r),
+                    rot: -30.,
+                    binding_cfg_id: 0,
+                };
+                for (port, &(dq, dr)) in HEX_NEIGHBOURS.iter().enumerate() {
+                    if self.is_frozen(q + dq, r + dr) {
+                        particle.set_port_busy(port as u8);
+                    }
+                }
+                particles.push(particle);
+            }
+        }
+        self.particles = particles;
+        self.particles.len()
+    }
+
+    pub fn static_particles_ptr(&self) -> *const StaticParticle {
+        self.particles.as_ptr()
+    }
+    pub fn static_particles_count(&self) -> usize {
+        self.particles.len()
+    }
+}
+
+impl Snowflake {
+    fn hex_distance(q: isize, r: isize) -> isize {
+        (q.abs() + r.abs() + (q + r).abs()) / 2
+    }
+
+    fn linear(&self, q: isize, r: isize) -> usize {
+        let side = 2 * self.radius + 1;
+        (q + self.radius as isize) as usize + (r + self.radius as isize) as usize * side
+    }
+
+    /// index of a cell in storage, None if the cell is outside the lattice
+    fn index(&self, q: isize, r: isize) -> Option<usize> {
+        if Self::hex_distance(q, r) <= self.radius as isize {
+            Some(self.linear(q, r))
+        } else {
+            None
+        }
+    }
+
+    fn is_frozen(&self, q: isize, r: isize) -> bool {
+        self.index(q, r)
+            .map(|index| self.cells[index] >= 1.)
+            .unwrap_or(false)
+    }
+
+    fn diffusing_at(&self, q: isize, r: isize) -> f64 {
+        self.index(q, r)
+            .map_or(self.beta, |index| self.diffusing[index])
+    }
+
+    /// field position of a cell, lattice center is at (0, 0)
+    fn position(&self, q: isize, r: isize) -> Vector {
+        Vector {
+            x: self.spacing * (q as f64 + r as f64 / 2.),
+            y: self.spacing * (r as f64 * 3f64.sqrt() / 2.),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn six_fold_symmetry() {
+        let mut flake = Snowflake::new(30, 5., 1., 0.4, 0.001);
+        assert_eq!(flake.frozen_count(), 1);
+        for _ in 0..200 {
+            if !flake.step() {
+                break;
+            }
+        }
+        assert!(flake.frozen_count() > 1, "Crystal did not grow");
+
+        // rotation by 60 degrees in axial coordinates: (q, r) -> (-r, q + r)
+        let radius = flake.radius as isize;
+        for r in -radius..=radius {
+            for q in -radius..=radius {
+                if flake.index(q, r).is_some() {
+                    assert_eq!(
+                        flake.is_frozen(q, r),
+                        flake.is_frozen(-r, q + r),
+                        "Asymmetric cell at ({}, {})",
+                        q,
+                        r
+                    );
+                }
+            }
+        }
+
+        assert_eq!(flake.export_static_particles(), flake.frozen_count());
+        assert_eq!(flake.static_particles_count(), flake.frozen_count());
+    }
+}
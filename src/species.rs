@@ -0,0 +1,111 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::walk::{self, WalkModel};
+
+/// Number of moving particle species a field can hold
+pub const MAX_SPECIES: usize = 4;
+
+/// Speed distribution of newly spawned particles and its decay with age
+#[derive(Copy, Clone, PartialEq)]
+pub struct Mobility {
+    // median initial speed
+    pub speed: f64,
+    // standard deviation of speed logarithm, 0 gives every particle the same speed
+    pub spread: f64,
+    // time for speed to drop e times, not positive values disable decay
+    pub decay_time: f64,
+}
+
+impl Mobility {
+    pub const fn new() -> Self {
+        Self {
+            speed: 1.,
+            spread: 0.,
+            decay_time: 0.,
+        }
+    }
+
+    /// random initial speed (log-normal distribution)
+    pub fn sample_speed(&self) -> f64 {
+        if self.spread > 0. {
+            self.speed * (self.spread * walk::gaussian_pair().x).exp()
+        } else {
+            self.speed
+        }
+    }
+
+    /// speed multiplier for a particle of given age
+    pub fn decay(&self, age: f64) -> f64 {
+        if self.decay_time > 0. {
+            (-age.max(0.) / self.decay_time).exp()
+        } else {
+            1.
+        }
+    }
+}
+
+/// Motion parameters shared by all moving particles of one species
+#[derive(Copy, Clone, PartialEq)]
+pub struct Species {
+    pub walk: WalkModel,
+    pub mobility: Mobility,
+    // rotational diffusion coefficient, degrees squared per time unit
+    pub rotational_diffusion: f64,
+}
+
+impl Species {
+    pub const fn new() -> Self {
+        Self {
+            walk: WalkModel::Legacy,
+            mobility: Mobility::new(),
+            rotational_diffusion: 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn mobility() {
+        let uniform = Mobility::new();
+        assert_eq!(uniform.sample_speed(), 1.);
+        assert_eq!(uniform.decay(100.), 1.);
+
+        let mixed = Mobility {
+            speed: 2.,
+            spread: 0.5,
+            decay_time: 10.,
+        };
+        let samples = 10000;
+        let below_median = (0..samples)
+            .map(|_| mixed.sample_speed())
+            .inspect(|&speed| assert!(speed > 0.))
+            .filter(|&speed| speed < 2.)
+            .count();
+        assert!(
+            (below_median as f64 / samples as f64 - 0.5).abs() < 0.03,
+            "Median is off: {} of {} below",
+            below_median,
+            samples
+        );
+        assert!((mixed.decay(10. * std::f64::consts::LN_2) - 0.5).abs() < 1e-12);
+        assert_eq!(mixed.decay(-1.), 1.);
+    }
+}
@@ -0,0 +1,111 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::f64::consts::PI;
+
+use crate::vector::Vector;
+
+// squared distance below which two images are considered the same point
+const COINCIDENCE_EPSILON: f64 = 1e-12;
+
+/// Symmetry group applied to growth: n-fold rotations around a center,
+/// optionally combined with reflections (dihedral group)
+#[derive(Copy, Clone, PartialEq)]
+pub struct Symmetry {
+    // number of rotations in the group, 1 means no rotational symmetry
+    order: u8,
+    // add mirror images across a horizontal axis through the center
+    reflect: bool,
+    center: Vector,
+}
+
+impl Symmetry {
+    pub fn new(order: u8, reflect: bool, center: Vector) -> Self {
+        Self {
+            order: order.max(1),
+            reflect,
+            center,
+        }
+    }
+
+    /// distinct images of a position under every group element except identity
+    /// (points on symmetry axes or at the center produce fewer images)
+    pub fn images(&self, pos: &Vector) -> Vec<Vector> {
+        let relative = Vector::diff(pos, &self.center);
+        let mirrored = Vector {
+            x: relative.x,
+            y: -relative.y,
+        };
+        let step = 2. * PI / self.order as f64;
+
+        let mut images = Vec::with_capacity(2 * self.order as usize);
+        let mut push_distinct = |image: Vector| {
+            let is_new = std::iter::once(pos)
+                .chain(images.iter())
+                .all(|other| Vector::distance_squared(other, &image) > COINCIDENCE_EPSILON);
+            if is_new {
+                images.push(image);
+            }
+        };
+        for k in 0..self.order {
+            if k > 0 {
+                push_distinct(self.center + Vector::rotate(&relative, step * k as f64));
+            }
+            if self.reflect {
+                push_distinct(self.center + Vector::rotate(&mirrored, step * k as f64));
+            }
+        }
+        images
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn group_images() {
+        let center = Vector::new(10., 0.);
+        let pos = Vector::new(12., 1.);
+
+        let rotations = Symmetry::new(4, false, center).images(&pos);
+        assert_eq!(rotations.len(), 3);
+        let expected = [(9., 2.), (8., -1.), (11., -2.)];
+        for (image, &(x, y)) in rotations.iter().zip(expected.iter()) {
+            assert!(
+                (image.x - x).abs() < 1e-9 && (image.y - y).abs() < 1e-9,
+                "Wrong rotation image ({}, {})",
+                image.x,
+                image.y
+            );
+        }
+
+        let dihedral = Symmetry::new(4, true, center).images(&pos);
+        assert_eq!(dihedral.len(), 7);
+        assert!((dihedral[0].x - 12.).abs() < 1e-9 && (dihedral[0].y + 1.).abs() < 1e-9);
+
+        assert!(Symmetry::new(1, false, center).images(&pos).is_empty());
+        assert!(Symmetry::new(6, true, center).images(&center).is_empty());
+        // a point on the mirror axis is its own reflection
+        assert_eq!(
+            Symmetry::new(4, true, center)
+                .images(&Vector::new(12., 0.))
+                .len(),
+            3
+        );
+    }
+}
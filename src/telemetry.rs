@@ -0,0 +1,143 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+/// Number of ticks kept in the telemetry ring buffer
+pub const TELEMETRY_CAPACITY: usize = 1024;
+
+/// Busy port histogram bins: 0 to 8 busy ports
+pub const PORT_HISTOGRAM_SIZE: usize = 9;
+
+/// Number of f64 values per sample in the telemetry buffer
+pub const SAMPLE_STRIDE: usize = 7 + PORT_HISTOGRAM_SIZE;
+
+/// Growth metrics recorded every tick
+#[derive(Copy, Clone, Default)]
+pub struct GrowthSample {
+    pub time: f64,
+    pub static_count: usize,
+    pub moving_count: usize,
+    // moving particles attached during the tick
+    pub attachments: usize,
+    // largest distance of a static particle from their center of mass
+    pub cluster_radius: f64,
+    // average age of moving particles
+    pub mean_wander_time: f64,
+    // static particles that can accept more bonds
+    pub active_sites: usize,
+    // number of static particles by count of busy ports
+    pub port_histogram: [u32; PORT_HISTOGRAM_SIZE],
+}
+
+impl GrowthSample {
+    /// Buffer layout (`Field::telemetry_view`), SAMPLE_STRIDE f64 values per sample:
+    /// [time, static_count, moving_count, attachments, cluster_radius,
+    /// mean_wander_time, active_sites, port_histogram[0..9]]
+    fn write_to(&self, out: &mut [f64]) {
+        out[..7].copy_from_slice(&[
+            self.time,
+            self.static_count as f64,
+            self.moving_count as f64,
+            self.attachments as f64,
+            self.cluster_radius,
+            self.mean_wander_time,
+            self.active_sites as f64,
+        ]);
+        for (value, &count) in out[7..].iter_mut().zip(self.port_histogram.iter()) {
+            *value = count as f64;
+        }
+    }
+}
+
+/// Ring buffer of growth samples, flat so that JS can view it as a typed array
+pub struct Telemetry {
+    buffer: Vec<f64>,
+    // slot of the oldest sample
+    head: usize,
+    // number of recorded samples
+    len: usize,
+}
+
+impl Telemetry {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: vec![0.; capacity.max(1) * SAMPLE_STRIDE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len() / SAMPLE_STRIDE
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn head(&self) -> usize {
+        self.head
+    }
+    pub fn as_ptr(&self) -> *const f64 {
+        self.buffer.as_ptr()
+    }
+    pub fn as_slice(&self) -> &[f64] {
+        &self.buffer
+    }
+
+    /// add a sample, overwriting the oldest one when full
+    pub fn record(&mut self, sample: &GrowthSample) {
+        let capacity = self.capacity();
+        let slot = (self.head + self.len) % capacity;
+        sample.write_to(&mut self.buffer[slot * SAMPLE_STRIDE..(slot + 1) * SAMPLE_STRIDE]);
+        if self.len < capacity {
+            self.len += 1;
+        } else {
+            self.head = (self.head + 1) % capacity;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn ring_buffer() {
+        let mut telemetry = Telemetry::new(3);
+        for tick in 0..5 {
+            telemetry.record(&GrowthSample {
+                time: tick as f64,
+                static_count: 10 + tick,
+                port_histogram: [1, 2, 3, 0, 0, 0, 0, 0, 9],
+                ..GrowthSample::default()
+            });
+        }
+        assert_eq!(telemetry.len(), 3);
+        assert_eq!(telemetry.head(), 2);
+        let oldest = &telemetry.buffer[2 * SAMPLE_STRIDE..3 * SAMPLE_STRIDE];
+        assert_eq!(oldest[..2], [2., 12.]);
+        assert_eq!(oldest[SAMPLE_STRIDE - 1], 9.);
+        assert_eq!(telemetry.buffer[0], 3.);
+        assert_eq!(telemetry.buffer[SAMPLE_STRIDE], 4.);
+
+        telemetry.clear();
+        assert_eq!((telemetry.len(), telemetry.head()), (0, 0));
+    }
+}
@@ -20,6 +20,7 @@ use wasm_bindgen::prelude::*;
 /// Vector
 #[wasm_bindgen]
 #[derive(Copy, Clone, PartialEq)]
+#[repr(C)]
 pub struct Vector {
     pub x: f64,
     pub y: f64,
@@ -60,6 +61,29 @@ impl Vector {
             y: to.y - from.y,
         }
     }
+
+    /// mirror a vector against a surface with given unit normal
+    pub fn reflect(v: &Vector, normal: &Vector) -> Vector {
+        *v - *normal * (2. * Vector::dot(v, normal))
+    }
+
+    /// difference between the closest images of two points in a periodic field
+    pub fn min_image_diff(to: &Vector, from: &Vector, period: &Vector) -> Vector {
+        let d = Vector::diff(to, from);
+        Vector {
+            x: d.x - period.x * (d.x / period.x).round(),
+            y: d.y - period.y * (d.y / period.y).round(),
+        }
+    }
+
+    /// rotate counter-clockwise around origin (radians)
+    pub fn rotate(v: &Vector, angle: f64) -> Vector {
+        let (sin, cos) = angle.sin_cos();
+        Vector {
+            x: v.x * cos - v.y * sin,
+            y: v.x * sin + v.y * cos,
+        }
+    }
 }
 
 impl Add for Vector {
@@ -0,0 +1,131 @@
+/*
+   Copyright 2021 Alexander Efremkin
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::f64::consts::PI;
+
+use crate::extfn;
+use crate::vector::Vector;
+
+/// random direction, vector with length 1
+pub fn random_direction() -> Vector {
+    let (sin, cos) = (extfn::random() * 2. * PI).sin_cos();
+    Vector { x: sin, y: cos }
+}
+
+/// pair of independent standard normal values (Box-Muller transform)
+pub fn gaussian_pair() -> Vector {
+    // 1 - random() is in (0, 1], so that the logarithm is finite
+    let radius = (-2. * (1. - extfn::random()).ln()).sqrt();
+    random_direction() * radius
+}
+
+/// Random walk of a moving particle.
+/// Every model turns current velocity and a deterministic drift (attractor, gravity)
+/// into a new velocity; positions then advance by `velocity * delta`
+#[derive(Copy, Clone, PartialEq)]
+pub enum WalkModel {
+    /// unit speed, direction nudged by a random unit vector every update
+    Legacy,
+    /// Brownian motion: Gaussian displacement with variance 2 * diffusion * delta per axis
+    Gaussian { diffusion: f64 },
+    /// run-and-tumble: unit speed straight runs, direction randomised at a given rate
+    Persistent { turn_rate: f64 },
+    /// Lévy flight: random direction jumps with power-law length distribution
+    /// P(l > x) = (min_step / x)^alpha, truncated at max_step
+    /// (keep max_step below attachment radius, or particles will tunnel through crystals)
+    Levy {
+        alpha: f64,
+        min_step: f64,
+        max_step: f64,
+    },
+}
+
+impl WalkModel {
+    /// new velocity after a time step
+    pub fn velocity(&self, vel: &Vector, drift: &Vector, delta: f64) -> Vector {
+        match *self {
+            WalkModel::Legacy => {
+                // velocity changes according to delta, but is always normalized afterwards
+                Vector::normalize(*vel + Vector::normalize(*drift + random_direction()) * delta)
+            }
+            WalkModel::Gaussian { diffusion } => {
+                if delta <= 0. {
+                    return *vel;
+                }
+                *drift + gaussian_pair() * (2. * diffusion / delta).sqrt()
+            }
+            WalkModel::Persistent { turn_rate } => {
+                let tumble = extfn::random() < 1. - (-turn_rate * delta).exp();
+                let direction = if tumble || Vector::length(vel) == 0. {
+                    random_direction()
+                } else {
+                    *vel
+                };
+                Vector::normalize(Vector::normalize(direction) + *drift * delta)
+            }
+            WalkModel::Levy {
+                alpha,
+                min_step,
+                max_step,
+            } => {
+                if delta <= 0. {
+                    return *vel;
+                }
+                let step = (min_step * (1. - extfn::random()).powf(-1. / alpha)).min(max_step);
+                *drift + random_direction() * (step / delta)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn walk_statistics() {
+        let still = Vector::new(0., 0.);
+        let delta = 0.5;
+        let samples = 20000;
+
+        // mean squared displacement per axis is 2 * D * delta
+        let gaussian = WalkModel::Gaussian { diffusion: 2. };
+        let msd = (0..samples)
+            .map(|_| {
+                let step = gaussian.velocity(&still, &still, delta) * delta;
+                Vector::dot(&step, &step) / 2.
+            })
+            .sum::<f64>()
+            / samples as f64;
+        assert!((msd - 2.).abs() < 0.1, "Wrong gaussian MSD {}", msd);
+
+        let levy = WalkModel::Levy {
+            alpha: 1.5,
+            min_step: 1.,
+            max_step: 4.,
+        };
+        for _ in 0..1000 {
+            let step = Vector::length(&levy.velocity(&still, &still, delta)) * delta;
+            assert!((1. - 1e-9..=4. + 1e-9).contains(&step), "Step {}", step);
+        }
+
+        let runner = WalkModel::Persistent { turn_rate: 0. };
+        let heading = Vector::new(0.6, 0.8);
+        let run = runner.velocity(&heading, &still, delta);
+        assert!(Vector::length(&Vector::diff(&run, &heading)) < 1e-12);
+    }
+}
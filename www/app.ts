@@ -115,6 +115,7 @@ class App {
         
         if (this.ready && !this.paused) {
             this.simulationTime += delta / 60.;
+            this.field.set_time(this.simulationTime);
             for (let tick = 0; tick < config.field.ticksPerCall; tick++) {
                 this.field.update_attachments();
 
@@ -218,15 +219,7 @@ class App {
         const mirrors = 3 + Math.trunc(Math.random() * 4)
         const pts = 1 + Math.trunc(mirrors / 2) + Math.trunc(Math.random() * (6 - mirrors / 2))
 
-        for (let i = 0; i < pts; i++) {
-            const th = Math.random() * Math.PI * 2;
-            const r = (.2 + Math.random()) * ((i + 1) * .5 / pts) * config.field.width;
-            for (let m = 0; m < mirrors; m++) {
-                const x = Math.sin(th + m * 2 * Math.PI / mirrors)
-                const y = Math.cos(th + m * 2 * Math.PI / mirrors)
-                this.field.add_static_particle(new wasm.Vector(x * r, y * r));
-            }
-        }
+        this.field.seed_symmetric_random(pts, mirrors, config.field.width)
     }
 }
 
@@ -15,7 +15,6 @@
 */
 
 import { MovingParticle, StaticParticle } from '../pkg/valo';
-import { memory } from '../pkg/valo_bg.wasm';
 import * as PIXI from 'pixi.js';
 
 import { app } from './app';
@@ -27,6 +26,19 @@ function addSprites(spriteContainer: PIXI.Container, numSprites: number) {
     }
 }
 
+// slots of values read from particle buffers
+const movingLayout = MovingParticle.layout();
+const movingStride = movingLayout.stride;
+const movingX = movingLayout.offset_of('pos.x')!;
+const movingY = movingLayout.offset_of('pos.y')!;
+const movingSince = movingLayout.offset_of('since')!;
+movingLayout.free();
+const staticLayout = StaticParticle.layout();
+const staticStride = staticLayout.stride;
+const staticX = staticLayout.offset_of('pos.x')!;
+const staticY = staticLayout.offset_of('pos.y')!;
+staticLayout.free();
+
 function updateVisibleParticles() {
     const num_moving_particles = app.field.moving_particles_count();
     const num_static_particles = app.field.static_particles_count();
@@ -34,37 +46,45 @@ function updateVisibleParticles() {
     regrowParticleContainer(app.staticParticlesContainer, num_static_particles)
 
     // update moving particles positions
-    const particle_size = MovingParticle.get_f64_size();
-    const movingParticlesView = new Float64Array(
-        memory.buffer,
-        app.field.moving_particles_ptr(),
-        num_moving_particles * particle_size);
+    // views are detached when wasm memory grows, so take them anew every frame
+    const movingParticlesView = app.field.moving_particles_view();
     const now = app.simulationTime;
     const alpha_offset = app.fieldBorder.alpha; // match border
 
     for (let i = 0; i < num_moving_particles; i++) {
         app.movingParticlesContainer.children[i].position.set(
-            movingParticlesView[i * particle_size], movingParticlesView[i * particle_size + 1])
+            movingParticlesView[i * movingStride + movingX], movingParticlesView[i * movingStride + movingY])
         // moving particles have a timestamp when they appeared; we use this timestamp
         // to have particles gradually achieve full glow, 
         // and offset initial glow to match border 
         app.movingParticlesContainer.children[i].alpha = Math.min(
             1.0,
-            alpha_offset + (now - movingParticlesView[i * particle_size + 4]) / (3));
+            alpha_offset + (now - movingParticlesView[i * movingStride + movingSince]) / (3));
     }
 
     // update static particles positions
-    const static_particle_size = StaticParticle.get_f64_size();
-    const staticParticlesView = new Float64Array(
-        memory.buffer,
-        app.field.static_particles_ptr(),
-        num_static_particles * static_particle_size);
+    const staticParticlesView = app.field.static_particles_view();
     for (let i = 0; i < num_static_particles; i++) {
         app.staticParticlesContainer.children[i].position.set(
-            staticParticlesView[i * static_particle_size], staticParticlesView[i * static_particle_size + 1])
+            staticParticlesView[i * staticStride + staticX], staticParticlesView[i * staticStride + staticY])
     }
 }
 
+// growth telemetry, one sample per simulation tick (see GrowthSample in telemetry.rs)
+const telemetryStaticCount = 1;
+const telemetryClusterRadius = 4;
+
+function growthSummary(): string {
+    const len = app.field.telemetry_len();
+    if (len === 0)
+        return '';
+    const stride = app.field.telemetry_stride();
+    const latest = (app.field.telemetry_head() + len - 1) % app.field.telemetry_capacity();
+    const telemetryView = app.field.telemetry_view();
+    return telemetryView[latest * stride + telemetryStaticCount] + ' particles, radius ' +
+        telemetryView[latest * stride + telemetryClusterRadius].toFixed(1);
+}
+
 function regrowParticleContainer(spriteContainer: PIXI.Container, actualCount: number) {
     if (spriteContainer.children.length < actualCount) {
         // add more sprites
@@ -76,5 +96,6 @@ function regrowParticleContainer(spriteContainer: PIXI.Container, actualCount: n
 }
 
 export {
-    updateVisibleParticles
+    updateVisibleParticles,
+    growthSummary
 }
\ No newline at end of file
@@ -16,6 +16,7 @@
 
 import { createApp } from './app'
 import config from './config'
+import { growthSummary } from './frame'
 
 // bind app to DOM
 function bindApp() {
@@ -79,6 +80,9 @@ function bindApp() {
         const elFps = document.getElementById('fps');
         if (elFps)
             elFps.innerHTML = app.lastFrameTime.toFixed(3);
+        const elGrowth = document.getElementById('growth');
+        if (elGrowth)
+            elGrowth.innerHTML = growthSummary();
     }, 667);
 }
 